@@ -0,0 +1,115 @@
+use std::fmt::Display;
+use std::io;
+
+use once_cell::sync::Lazy;
+use tokio::io::{AsyncBufReadExt, BufReader, Stdin};
+use tokio::sync::Mutex;
+
+use crate::xmt::{parse_yn, yn_question, PICK_PROMPT};
+use crate::XMT;
+
+/// The reader of stdin shared by all prompts, so that input buffered past a line is kept for the next prompt.
+static STDIN: Lazy<Mutex<BufReader<Stdin>>> =
+    Lazy::new(|| Mutex::new(BufReader::new(tokio::io::stdin())));
+
+async fn read_line() -> io::Result<String> {
+    let mut user_input = String::new();
+    STDIN.lock().await.read_line(&mut user_input).await?;
+    Ok(user_input)
+}
+
+impl XMT {
+    /// Prompt the user for a yes/no answer without blocking the executor.
+    ///
+    /// # Errors
+    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use xmt::XMT;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let xmt = XMT::default();
+    /// if xmt.prompt_yn_async("Are you sure?", false).await? {
+    ///     // do the thing
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// `true` if the user answered yes, `false` if the user answered no.
+    pub async fn prompt_yn_async(&self, msg: &str, default: bool) -> io::Result<bool> {
+        self.check_interactive()?;
+        self.print_prompt(&yn_question(msg, default))?;
+
+        let user_input = read_line().await?;
+        Ok(parse_yn(&user_input, default))
+    }
+
+    /// Prompt the user for input without blocking the executor.
+    ///
+    /// # Errors
+    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use xmt::XMT;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let xmt = XMT::default();
+    /// let name = xmt.prompt_async("What is your name?").await?;
+    /// println!("Hello, {}!", name);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The text entered by the user.
+    pub async fn prompt_async(&self, msg: &str) -> io::Result<String> {
+        self.check_interactive()?;
+        self.print_prompt(msg)?;
+
+        let user_input = read_line().await?;
+        Ok(String::from(user_input.trim()))
+    }
+
+    /// Prompt the user to select an item from a list without blocking the executor.
+    ///
+    /// # Errors
+    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use xmt::XMT;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let xmt = XMT::default();
+    ///
+    /// let choices = vec!["foo", "bar", "baz"];
+    /// let pick = xmt.pick_async("Pick one", &choices).await?;
+    /// println!("You picked: {}", pick);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// A reference to the item selected by the user.
+    pub async fn pick_async<'a, E: Display>(&self, msg: &str, items: &'a [E]) -> io::Result<&'a E> {
+        self.check_interactive()?;
+        self.print_choices(msg, items);
+
+        let pick_idx = loop {
+            self.print_prompt(PICK_PROMPT)?;
+            let pick = read_line().await?;
+            if let Some(idx) = self.parse_pick(pick.trim(), items.len()) {
+                break idx;
+            }
+        };
+
+        Ok(&items[pick_idx])
+    }
+}
@@ -0,0 +1,78 @@
+//! Standard output flags for [clap](https://docs.rs/clap) parsers.
+
+use clap::Args;
+
+use crate::{ColorChoice, Config, OutputMode};
+
+/// The environment variable setting the default of `--output`.
+pub const OUTPUT_ENV_VAR: &str = "XMT_OUTPUT";
+
+/// The environment variable setting the default of `--color`.
+pub const COLOR_ENV_VAR: &str = "XMT_COLOR";
+
+/// The environment variable setting the default of `--no-input`.
+pub const NO_INPUT_ENV_VAR: &str = "XMT_NO_INPUT";
+
+/// Output flags shared by command-line tools, meant to be flattened into a [clap](https://docs.rs/clap) parser.
+///
+/// # Example
+/// ```rust
+/// use clap::Parser;
+/// use xmt::cli::OutputArgs;
+/// use xmt::{ColorChoice, Config, OutputMode};
+///
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[command(flatten)]
+///     output: OutputArgs,
+/// }
+///
+/// let cli = Cli::parse_from(["app", "--output", "json", "--color", "never", "-vv", "-q"]);
+/// let cfg = Config::from(cli.output);
+/// assert_eq!(cfg.output, OutputMode::JSON);
+/// assert_eq!(cfg.color, ColorChoice::Never);
+/// assert_eq!(cfg.verbosity, 1);
+///
+/// xmt::init(cfg);
+/// ```
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OutputArgs {
+    /// The output format of structured data.
+    #[arg(long, value_enum, env = OUTPUT_ENV_VAR, default_value_t = OutputMode::Text)]
+    pub output: OutputMode,
+
+    /// When to color the output.
+    #[arg(long, value_enum, env = COLOR_ENV_VAR, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Print more messages. Can be repeated.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Print fewer messages. Can be repeated.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub quiet: u8,
+
+    /// Never prompt for input.
+    #[arg(long, env = NO_INPUT_ENV_VAR)]
+    pub no_input: bool,
+}
+
+impl OutputArgs {
+    /// The verbosity set by the flags, i.e. the number of `-v` minus the number of `-q`.
+    pub fn verbosity(&self) -> i8 {
+        let verbosity = i16::from(self.verbose) - i16::from(self.quiet);
+        verbosity.clamp(i16::from(i8::MIN), i16::from(i8::MAX)) as i8
+    }
+}
+
+impl From<OutputArgs> for Config {
+    fn from(args: OutputArgs) -> Self {
+        let mut cfg = Config::default()
+            .with_color(args.color)
+            .with_verbosity(args.verbosity())
+            .with_interactive(!args.no_input);
+        cfg.output = args.output;
+        cfg
+    }
+}
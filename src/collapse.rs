@@ -0,0 +1,201 @@
+use std::sync::Arc;
+use std::thread;
+
+use parking_lot::Mutex;
+
+use crate::{Level, Outcome, Stream, XMT};
+
+/// The output of a collapsed scope.
+pub(crate) type Buffer = Arc<Mutex<Transcript>>;
+
+/// The rendered lines of a collapsed scope.
+#[derive(Default)]
+pub(crate) struct Transcript {
+    /// The lines, with the stream they were meant for.
+    pub(crate) lines: Vec<(Stream, String)>,
+
+    /// Whether a warning or an error was printed in the scope.
+    pub(crate) problems: bool,
+
+    /// The rendered message of the scope, printed when the scope is expanded.
+    header: Option<String>,
+
+    /// The buffer of the enclosing collapsed scope, if any.
+    parent: Option<Buffer>,
+
+    /// Whether the scope was expanded before a prompt, in which case lines are no longer buffered.
+    expanded: bool,
+}
+
+/// Write a line to a buffer, or to its stream if there is no buffer or if it was expanded.
+pub(crate) fn write(buffer: Option<&Buffer>, stream: Stream, line: String) {
+    let Some(buffer) = buffer else {
+        match stream {
+            Stream::Stdout => println!("{line}"),
+            Stream::Stderr => eprintln!("{line}"),
+        }
+        return;
+    };
+
+    let mut transcript = buffer.lock();
+    if !transcript.expanded {
+        transcript.lines.push((stream, line));
+        return;
+    }
+    let parent = transcript.parent.clone();
+    drop(transcript);
+    write(parent.as_ref(), stream, line);
+}
+
+/// Print the message and the buffered output of a collapsed scope and of the scopes enclosing it,
+/// so that the context of a prompt is visible.
+pub(crate) fn expand(buffer: &Buffer) {
+    let mut transcript = buffer.lock();
+    if transcript.expanded {
+        return;
+    }
+    transcript.expanded = true;
+    let header = transcript.header.take();
+    let lines = std::mem::take(&mut transcript.lines);
+    let parent = transcript.parent.clone();
+    drop(transcript);
+
+    if let Some(parent) = &parent {
+        expand(parent);
+    }
+    if let Some(header) = header {
+        write(parent.as_ref(), Stream::Stdout, header);
+    }
+    for (stream, line) in lines {
+        write(parent.as_ref(), stream, line);
+    }
+}
+
+/// A collapsed scope being executed.
+///
+/// If dropped without being closed, i.e. when unwinding from a panic, its buffered output is replayed.
+pub(crate) struct CollapsedScope<'a> {
+    xmt: &'a XMT,
+    message: &'a str,
+    buffer: Option<Buffer>,
+}
+
+impl CollapsedScope<'_> {
+    /// The buffer of the scope, if its output is collapsed.
+    pub(crate) fn buffer(&self) -> Option<Buffer> {
+        self.buffer.clone()
+    }
+
+    /// Close the scope, replaying its buffered output if it failed or printed warnings or errors.
+    pub(crate) fn close(mut self, failed: bool) {
+        let buffer = self.buffer.take();
+        let xmt = self.xmt;
+        xmt.flush_repeat();
+
+        let replayed = buffer.filter(|buffer| {
+            let transcript = buffer.lock();
+            !transcript.expanded && (failed || transcript.problems)
+        });
+        if let Some(buffer) = replayed {
+            xmt.replay(self.message, &buffer);
+        }
+
+        if failed {
+            xmt.error(&format!("{} failed", self.message));
+        } else {
+            xmt.success(self.message);
+        }
+    }
+}
+
+impl Drop for CollapsedScope<'_> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            if thread::panicking() && !buffer.lock().expanded {
+                self.xmt.replay(self.message, &buffer);
+            }
+        }
+    }
+}
+
+impl XMT {
+    /// Execute the provided closure in a collapsed scope.
+    ///
+    /// The output of the scope is buffered. If the closure succeeds, the scope collapses into a single success line.
+    /// If it returned an `Err` or panicked, the buffered output is replayed with its indentation, followed by an error line
+    /// unless it panicked. The output is also replayed, before the success line, if a warning or an error was printed in the scope.
+    ///
+    /// Before a prompt, the buffered output is printed and the rest of the scope is not buffered,
+    /// so that the question is shown with its context.
+    ///
+    /// Output is not buffered when stdout is not a TTY or when messages are printed as JSON:
+    /// the scope is printed as with [XMT::nest](crate::XMT::nest), followed by the success or error line.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::XMT;
+    ///
+    /// let xmt = XMT::default();
+    /// xmt.nest_collapsed("Building", |xmt| {
+    ///     xmt.print("Compiling");
+    ///     xmt.print("Linking");
+    /// });
+    ///
+    /// // Prints:
+    /// // ✔ Building
+    /// ```
+    pub fn nest_collapsed<T: Outcome, F: FnOnce(&XMT) -> T>(&self, message: &str, func: F) -> T {
+        let collapsed = self.open_collapsed(message);
+
+        let mut nested = self.nest();
+        if let Some(buffer) = collapsed.buffer() {
+            nested.buffer = Some(buffer);
+        }
+        let ret_val = func(&nested);
+        nested.close_scope();
+
+        collapsed.close(ret_val.is_failure());
+        ret_val
+    }
+
+    /// Open a collapsed scope.
+    ///
+    /// If output can't be collapsed, the message is printed right away and the scope has no buffer.
+    pub(crate) fn open_collapsed<'a>(&'a self, message: &'a str) -> CollapsedScope<'a> {
+        let buffer = if self.is_collapsible() {
+            let header = self.is_printed(&Level::Normal).then(|| {
+                let style = self.level_spec(&Level::Normal).style;
+                self.render(Stream::Stdout, message, &[], style)
+            });
+            Some(Arc::new(Mutex::new(Transcript {
+                header,
+                parent: self.buffer(),
+                ..Transcript::default()
+            })))
+        } else {
+            self.print(message);
+            None
+        };
+
+        CollapsedScope {
+            xmt: self,
+            message,
+            buffer,
+        }
+    }
+
+    /// Print the message of a collapsed scope, followed by its buffered output.
+    fn replay(&self, message: &str, buffer: &Buffer) {
+        let transcript = std::mem::take(&mut *buffer.lock());
+        if transcript.problems {
+            if let Some(outer) = self.buffer() {
+                outer.lock().problems = true;
+            }
+        }
+
+        self.print(message);
+        for (stream, line) in transcript.lines {
+            self.write_line(stream, line);
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::{Attributes, ColorChoice, ColorDepth};
+
+/// Colors supported by the library.
+///
+/// Colors can be parsed from their lowercase name, with bright variants prefixed by `bright `
+/// (e.g. `"red"` or `"bright blue"`), from a hex RGB value (e.g. `"#ff8700"` or `"#f80"`),
+/// or from a 256-color palette index (e.g. `"208"`).
+///
+/// Colors that are not supported by the terminal are mapped to the closest supported color.
+///
+/// # Examples
+/// ```rust
+/// use xmt::Color;
+///
+/// assert_eq!("bright blue".parse(), Ok(Color::BrightBlue));
+/// assert_eq!("#ff8700".parse(), Ok(Color::TrueColor { r: 255, g: 135, b: 0 }));
+/// assert_eq!("208".parse(), Ok(Color::Ansi256(208)));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// A color of the 256-color palette.
+    Ansi256(u8),
+    TrueColor {
+        r: u8,
+        g: u8,
+        b: u8,
+    },
+}
+
+const BASIC_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+/// RGB values of the basic colors in the default xterm palette.
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Channel values of the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Error returned when parsing an unknown color.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown color: '{}'", self.0)
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let name = src.trim().to_lowercase().replace(['_', '-'], " ");
+
+        match name.as_ref() {
+            "black" => Ok(Color::Black),
+            "red" => Ok(Color::Red),
+            "green" => Ok(Color::Green),
+            "yellow" => Ok(Color::Yellow),
+            "blue" => Ok(Color::Blue),
+            "magenta" | "purple" => Ok(Color::Magenta),
+            "cyan" => Ok(Color::Cyan),
+            "white" => Ok(Color::White),
+            "bright black" | "gray" | "grey" => Ok(Color::BrightBlack),
+            "bright red" => Ok(Color::BrightRed),
+            "bright green" => Ok(Color::BrightGreen),
+            "bright yellow" => Ok(Color::BrightYellow),
+            "bright blue" => Ok(Color::BrightBlue),
+            "bright magenta" | "bright purple" => Ok(Color::BrightMagenta),
+            "bright cyan" => Ok(Color::BrightCyan),
+            "bright white" => Ok(Color::BrightWhite),
+            _ => {
+                if let Some(hex) = name.strip_prefix('#') {
+                    parse_hex(hex).ok_or_else(|| ParseColorError(String::from(src)))
+                } else {
+                    name.parse()
+                        .map(Color::Ansi256)
+                        .map_err(|_| ParseColorError(String::from(src)))
+                }
+            }
+        }
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        3 => {
+            let (r, g, b) = (
+                channel(&hex[0..1])?,
+                channel(&hex[1..2])?,
+                channel(&hex[2..3])?,
+            );
+            Some(Color::TrueColor {
+                r: r * 17,
+                g: g * 17,
+                b: b * 17,
+            })
+        }
+        6 => Some(Color::TrueColor {
+            r: channel(&hex[0..2])?,
+            g: channel(&hex[2..4])?,
+            b: channel(&hex[4..6])?,
+        }),
+        _ => None,
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a color name, a hex RGB value or a 256-color palette index"
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(de::Error::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        u8::try_from(v)
+            .map(Color::Ansi256)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        u8::try_from(v)
+            .map(Color::Ansi256)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+impl Color {
+    /// The RGB value of the color, using the default xterm palette for palette colors.
+    fn rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Color::TrueColor { r, g, b } => (r, g, b),
+            Color::Ansi256(idx) => ansi256_rgb(idx),
+            basic => {
+                let idx = BASIC_COLORS
+                    .iter()
+                    .position(|c| *c == basic)
+                    .expect("all other colors are basic colors");
+                BASIC_RGB[idx]
+            }
+        }
+    }
+
+    /// Map the color to the closest color available at the provided depth.
+    pub(crate) fn downsample(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (Color::TrueColor { r, g, b }, ColorDepth::Ansi256) => {
+                Color::Ansi256(nearest_ansi256((r, g, b)))
+            }
+            (Color::Ansi256(idx), ColorDepth::Ansi16) if idx < 16 => BASIC_COLORS[idx as usize],
+            (Color::TrueColor { .. } | Color::Ansi256(_), ColorDepth::Ansi16) => {
+                let rgb = self.rgb();
+                let idx = (0..BASIC_RGB.len())
+                    .min_by_key(|i| distance(rgb, BASIC_RGB[*i]))
+                    .expect("palette is not empty");
+                BASIC_COLORS[idx]
+            }
+            _ => self,
+        }
+    }
+
+    fn fg_code(&self) -> String {
+        match *self {
+            Color::Black => String::from("30"),
+            Color::Red => String::from("31"),
+            Color::Green => String::from("32"),
+            Color::Yellow => String::from("33"),
+            Color::Blue => String::from("34"),
+            Color::Magenta => String::from("35"),
+            Color::Cyan => String::from("36"),
+            Color::White => String::from("37"),
+            Color::BrightBlack => String::from("90"),
+            Color::BrightRed => String::from("91"),
+            Color::BrightGreen => String::from("92"),
+            Color::BrightYellow => String::from("93"),
+            Color::BrightBlue => String::from("94"),
+            Color::BrightMagenta => String::from("95"),
+            Color::BrightCyan => String::from("96"),
+            Color::BrightWhite => String::from("97"),
+            Color::Ansi256(idx) => format!("38;5;{idx}"),
+            Color::TrueColor { r, g, b } => format!("38;2;{r};{g};{b}"),
+        }
+    }
+
+    fn bg_code(&self) -> String {
+        match *self {
+            Color::Ansi256(idx) => format!("48;5;{idx}"),
+            Color::TrueColor { r, g, b } => format!("48;2;{r};{g};{b}"),
+            _ => {
+                // Background codes are offset by 10 from their foreground counterparts.
+                let fg: u8 = self
+                    .fg_code()
+                    .parse()
+                    .expect("basic colors have numeric codes");
+                (fg + 10).to_string()
+            }
+        }
+    }
+}
+
+fn ansi256_rgb(idx: u8) -> (u8, u8, u8) {
+    match idx {
+        0..=15 => BASIC_RGB[idx as usize],
+        16..=231 => {
+            let idx = idx - 16;
+            (
+                CUBE_LEVELS[(idx / 36) as usize],
+                CUBE_LEVELS[((idx / 6) % 6) as usize],
+                CUBE_LEVELS[(idx % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (idx - 232);
+            (level, level, level)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// The closest color of the 6x6x6 cube or of the grayscale ramp of the 256-color palette.
+///
+/// The first 16 colors are skipped because terminals commonly customize them.
+fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |v: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - v as i32).abs())
+            .expect("cube levels are not empty") as u8
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+
+    let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + (avg.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(rgb, ansi256_rgb(gray)) < distance(rgb, ansi256_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Detect the colors supported by the terminal from `COLORTERM` and `TERM`.
+pub(crate) fn detect_depth() -> ColorDepth {
+    if env::var("COLORTERM").is_ok_and(|v| v == "truecolor" || v == "24bit") {
+        return ColorDepth::TrueColor;
+    }
+
+    match env::var("TERM") {
+        Ok(term) if term.ends_with("-direct") => ColorDepth::TrueColor,
+        Ok(term) if term.contains("256color") => ColorDepth::Ansi256,
+        _ => ColorDepth::Ansi16,
+    }
+}
+
+/// Wrap text in the ANSI escape sequences for the provided colors and attributes.
+///
+/// Colors are downsampled to the provided depth.
+pub(crate) fn paint(
+    text: &str,
+    color: Option<Color>,
+    background: Option<Color>,
+    attrs: Attributes,
+    depth: ColorDepth,
+) -> String {
+    let mut codes = Vec::new();
+    if attrs.bold {
+        codes.push(String::from("1"));
+    }
+    if attrs.dim {
+        codes.push(String::from("2"));
+    }
+    if attrs.italic {
+        codes.push(String::from("3"));
+    }
+    if attrs.underline {
+        codes.push(String::from("4"));
+    }
+    if attrs.reverse {
+        codes.push(String::from("7"));
+    }
+    if attrs.strikethrough {
+        codes.push(String::from("9"));
+    }
+    if let Some(color) = color {
+        codes.push(color.downsample(depth).fg_code());
+    }
+    if let Some(bg) = background {
+        codes.push(bg.downsample(depth).bg_code());
+    }
+
+    if codes.is_empty() || text.is_empty() {
+        return String::from(text);
+    }
+
+    format!("\x1b[{}m{text}\x1b[0m", codes.join(";"))
+}
+
+/// Whether output to a stream should be colored.
+///
+/// In [ColorChoice::Auto](crate::ColorChoice::Auto), follows the `NO_COLOR`, `CLICOLOR_FORCE`,
+/// `TERM=dumb` and `CLICOLOR` conventions, in that order, and colors TTYs otherwise.
+pub(crate) fn should_colorize(choice: ColorChoice, tty: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                return false;
+            }
+            if let Ok(force) = env::var("CLICOLOR_FORCE") {
+                if !force.is_empty() && force != "0" {
+                    return true;
+                }
+            }
+            if env::var("TERM").is_ok_and(|term| term == "dumb") {
+                return false;
+            }
+            if env::var("CLICOLOR").is_ok_and(|v| v == "0") {
+                return false;
+            }
+            tty
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_colors() {
+        assert_eq!(
+            parse_hex("ff8700"),
+            Some(Color::TrueColor {
+                r: 255,
+                g: 135,
+                b: 0
+            })
+        );
+        assert_eq!(
+            parse_hex("f80"),
+            Some(Color::TrueColor {
+                r: 255,
+                g: 136,
+                b: 0
+            })
+        );
+        assert_eq!(parse_hex("ff87"), None);
+        assert_eq!(parse_hex("gg8700"), None);
+        assert_eq!(parse_hex("é8700"), None);
+    }
+
+    #[test]
+    fn nearest_ansi256_colors() {
+        assert_eq!(nearest_ansi256((255, 135, 0)), 208);
+        assert_eq!(nearest_ansi256((0, 0, 0)), 16);
+        assert_eq!(nearest_ansi256((255, 255, 255)), 231);
+        assert_eq!(nearest_ansi256((128, 128, 128)), 244);
+        assert!((232..=255).contains(&nearest_ansi256((40, 42, 41))));
+    }
+
+    #[test]
+    fn downsample_colors() {
+        let orange = Color::TrueColor {
+            r: 255,
+            g: 135,
+            b: 0,
+        };
+        assert_eq!(orange.downsample(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.downsample(ColorDepth::Ansi256), Color::Ansi256(208));
+
+        let red = Color::TrueColor {
+            r: 200,
+            g: 10,
+            b: 10,
+        };
+        assert_eq!(red.downsample(ColorDepth::Ansi16), Color::Red);
+        let blue = Color::TrueColor {
+            r: 100,
+            g: 100,
+            b: 255,
+        };
+        assert_eq!(blue.downsample(ColorDepth::Ansi16), Color::BrightBlue);
+
+        assert_eq!(
+            Color::Ansi256(9).downsample(ColorDepth::Ansi16),
+            Color::BrightRed
+        );
+        assert_eq!(
+            Color::Ansi256(244).downsample(ColorDepth::Ansi16),
+            Color::BrightBlack
+        );
+        assert_eq!(Color::Cyan.downsample(ColorDepth::Ansi16), Color::Cyan);
+    }
+}
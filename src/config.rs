@@ -1,9 +1,17 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
 
-use colored::Color;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::{Color, Indent, Theme};
 
 /// Different output levels supported by the library.
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Level {
     /// Normal logging level.
     Normal,
@@ -26,23 +34,222 @@ pub enum Level {
 
     /// Errors. Printed to stderr.
     Error,
+
+    /// A user-defined level.
+    ///
+    /// Its style, stream and verbosity threshold are registered with [Config::with_level](crate::Config::with_level).
+    Custom(String),
+}
+
+impl Level {
+    /// Create a user-defined level.
+    ///
+    /// Names of built-in levels resolve to the built-in level, as when converting from a `&str`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use xmt::Level;
+    ///
+    /// assert_eq!(Level::custom("notice"), Level::Custom(String::from("notice")));
+    /// assert_eq!(Level::custom("warn"), Level::Warn);
+    /// ```
+    pub fn custom(name: &str) -> Self {
+        Self::from(name)
+    }
+
+    /// The name of the level, as used in configuration and JSON messages.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Normal => "normal",
+            Self::Prompt => "prompt",
+            Self::Success => "success",
+            Self::Detail => "detail",
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Custom(name) => name,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Level {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::from(name.as_str()))
+    }
+}
+
+impl From<&str> for Level {
+    /// Resolve a level from its name.
+    ///
+    /// Names that do not match a built-in level produce a [Level::Custom](crate::Level::Custom).
+    fn from(name: &str) -> Self {
+        match name {
+            "normal" => Self::Normal,
+            "prompt" => Self::Prompt,
+            "success" => Self::Success,
+            "detail" => Self::Detail,
+            "warn" => Self::Warn,
+            "error" => Self::Error,
+            _ => Self::Custom(String::from(name)),
+        }
+    }
+}
+
+/// The stream a level is printed to.
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Stream {
+    /// Standard output.
+    #[default]
+    Stdout,
+
+    /// Standard error.
+    Stderr,
+}
+
+/// Text attributes applied on top of colors.
+///
+/// # Examples
+/// ```rust
+/// use xmt::Attributes;
+///
+/// let attrs = Attributes::default().bold().underline();
+/// assert!(attrs.bold && attrs.underline);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Attributes {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub reverse: bool,
+}
+
+impl Attributes {
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn dim(mut self) -> Self {
+        self.dim = true;
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+}
+
+/// The style of a prefix, when it differs from the style of the message.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PrefixStyle {
+    /// The color of the prefix.
+    ///
+    /// Defaults to the color of the message.
+    pub color: Option<Color>,
+
+    /// The background color of the prefix.
+    pub background: Option<Color>,
+
+    /// The text attributes of the prefix.
+    #[serde(flatten)]
+    pub attributes: Attributes,
+}
+
+impl PrefixStyle {
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn with_attributes(mut self, attributes: Attributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
 }
 
 /// A style for a given level.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// # Examples
+/// ```rust
+/// use xmt::{Attributes, Color, PrefixStyle, Style};
+///
+/// // A bold red "!" followed by a normal-weight red message.
+/// Style::new(Color::Red)
+///     .with_prefix(String::from("!"))
+///     .with_prefix_style(PrefixStyle::default().with_attributes(Attributes::default().bold()));
+///
+/// // The same style, in a theme file.
+/// let style: Style = toml::from_str(r#"
+///     prefix = "!"
+///     color = "red"
+///     prefix_style = { bold = true }
+/// "#).unwrap();
+/// assert!(style.prefix_style.unwrap().attributes.bold);
+/// assert!(!style.attributes.bold);
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
 pub struct Style {
     /// The prefix to use for the level.
+    #[serde(default)]
     pub prefix: Option<String>,
 
+    /// The ASCII prefix to use instead of the prefix when the terminal does not support Unicode.
+    #[serde(default)]
+    pub fallback_prefix: Option<String>,
+
     /// The color to use for the level.
     pub color: Color,
+
+    /// The background color to use for the level.
+    #[serde(default)]
+    pub background: Option<Color>,
+
+    /// The text attributes of the message.
+    #[serde(flatten)]
+    pub attributes: Attributes,
+
+    /// The style of the prefix.
+    ///
+    /// When not set, the prefix is styled like the message.
+    #[serde(default)]
+    pub prefix_style: Option<PrefixStyle>,
 }
 
 impl Style {
     pub fn new(color: Color) -> Self {
         Self {
             prefix: None,
+            fallback_prefix: None,
             color,
+            background: None,
+            attributes: Attributes::default(),
+            prefix_style: None,
         }
     }
 
@@ -50,14 +257,190 @@ impl Style {
         self.prefix = Some(prefix);
         self
     }
+
+    pub fn with_fallback_prefix(mut self, fallback_prefix: String) -> Self {
+        self.fallback_prefix = Some(fallback_prefix);
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn with_attributes(mut self, attributes: Attributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    pub fn with_prefix_style(mut self, prefix_style: PrefixStyle) -> Self {
+        self.prefix_style = Some(prefix_style);
+        self
+    }
+}
+
+/// Definition of a user-defined level.
+///
+/// # Examples
+/// ```rust
+/// use xmt::{Color, CustomLevel, Stream, Style};
+///
+/// CustomLevel::new(Style::new(Color::Cyan).with_prefix(String::from("~")))
+///     .with_stream(Stream::Stderr)
+///     .with_min_verbosity(1)
+///     .with_json_name("debug");
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct CustomLevel {
+    /// The style to use for the level.
+    pub style: Style,
+
+    /// The stream the level is printed to.
+    #[serde(default)]
+    pub stream: Stream,
+
+    /// The minimum [verbosity](crate::Config::verbosity) required for the level to be printed.
+    #[serde(default)]
+    pub min_verbosity: i8,
+
+    /// The value of the `level` field of JSON messages.
+    ///
+    /// Defaults to the name of the level.
+    #[serde(default)]
+    pub json_name: Option<String>,
+}
+
+impl CustomLevel {
+    /// Create a level printed to stdout at the default verbosity.
+    pub fn new(style: Style) -> Self {
+        Self {
+            style,
+            stream: Stream::Stdout,
+            min_verbosity: 0,
+            json_name: None,
+        }
+    }
+
+    pub fn with_stream(mut self, stream: Stream) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn with_min_verbosity(mut self, min_verbosity: i8) -> Self {
+        self.min_verbosity = min_verbosity;
+        self
+    }
+
+    pub fn with_json_name(mut self, json_name: &str) -> Self {
+        self.json_name = Some(String::from(json_name));
+        self
+    }
+}
+
+/// A limit on how often messages with the same key are printed.
+///
+/// When deserialized, the period is either a number of seconds, or a string of whole numbers
+/// followed by one of the units `ms`, `s`, `m` or `h` (e.g. `"500ms"`, `"5s"` or `"1m30s"`).
+///
+/// # Examples
+/// ```rust
+/// use std::time::Duration;
+/// use xmt::{Config, RateLimit};
+///
+/// Config::default().with_rate_limit("slow-request", RateLimit::new(5, Duration::from_secs(60)));
+///
+/// let cfg: Config = serde_json::from_str(
+///     r#"{"rate_limits": {"slow-request": {"max": 5, "period": "1m30s"}, "retrying": {"max": 1, "period": 10}}}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(cfg.rate_limits["slow-request"].period, Duration::from_secs(90));
+/// assert_eq!(cfg.rate_limits["retrying"].period, Duration::from_secs(10));
+/// assert!(serde_json::from_str::<RateLimit>(r#"{"max": 1, "period": "5 weeks"}"#).is_err());
+/// ```
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The number of messages printed per period.
+    pub max: usize,
+
+    /// The period after which the limit resets.
+    #[serde(deserialize_with = "deserialize_period")]
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(max: usize, period: Duration) -> Self {
+        Self { max, period }
+    }
+}
+
+/// Parse a period made of whole numbers followed by a unit, e.g. `"1m30s"`.
+fn parse_period(src: &str) -> Option<Duration> {
+    let mut rest = src.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut millis: u64 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit_end = rest[digits..]
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .map_or(rest.len(), |end| digits + end);
+
+        let value: u64 = rest[..digits].parse().ok()?;
+        let unit = match &rest[digits..unit_end] {
+            "ms" => 1,
+            "s" => 1_000,
+            "m" => 60_000,
+            "h" => 3_600_000,
+            _ => return None,
+        };
+        millis = millis.checked_add(value.checked_mul(unit)?)?;
+        rest = rest[unit_end..].trim_start();
+    }
+
+    Some(Duration::from_millis(millis))
+}
+
+struct PeriodVisitor;
+
+impl<'de> Visitor<'de> for PeriodVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number of seconds or a duration such as \"5s\"")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        parse_period(v).ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Duration::from_secs(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        u64::try_from(v)
+            .map(Duration::from_secs)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+}
+
+fn deserialize_period<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    deserializer.deserialize_any(PeriodVisitor)
 }
 
 /// The different output modes supported by the library.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
 pub enum OutputMode {
     /// Prints to stdout using [Display](std::fmt::Display).
     ///
     /// Default.
+    #[default]
     Text,
 
     /// Prints a tree to stdout using [Serialize](serde::Serialize).
@@ -69,14 +452,79 @@ pub enum OutputMode {
     JSON,
 }
 
-impl Default for OutputMode {
-    fn default() -> Self {
-        Self::Text
-    }
+/// The different formats messages can be printed in.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageFormat {
+    /// Prints messages as styled text.
+    ///
+    /// Messages are omitted when the output mode is [OutputMode::JSON](crate::OutputMode::JSON).
+    ///
+    /// Default.
+    #[default]
+    Text,
+
+    /// Prints each message as a JSON object on a single line.
+    ///
+    /// The object has a `level` and a `message` field along with the fields of the message,
+    /// and is printed to the stream of its level.
+    JSON,
+
+    /// Prints each message as a line of logfmt pairs.
+    ///
+    /// The line starts with a `level` and a `msg` pair, followed by the fields of the message,
+    /// and is printed to the stream of its level.
+    Logfmt,
+}
+
+/// When to color the output.
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Colors streams that are TTYs, unless disabled by the environment.
+    ///
+    /// Follows the `NO_COLOR`, `CLICOLOR_FORCE`, `CLICOLOR` and `TERM=dumb` conventions.
+    ///
+    /// Default.
+    #[default]
+    Auto,
+
+    /// Always colors the output, even when it is not a TTY.
+    Always,
+
+    /// Never colors the output.
+    Never,
+}
+
+/// The colors supported by a terminal.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorDepth {
+    /// The 16 basic colors.
+    Ansi16,
+
+    /// The 256-color palette.
+    Ansi256,
+
+    /// 24-bit RGB colors.
+    TrueColor,
 }
 
 /// Configuration for the XMT logger.
-#[derive(Clone, Default, PartialEq, Eq)]
+///
+/// Can be deserialized from any format supported by [serde](https://serde.rs).
+/// Missing fields take their default value.
+///
+/// # Examples
+/// ```rust
+/// use xmt::{Config, OutputMode};
+///
+/// let cfg: Config = serde_json::from_str(r#"{"output": "json", "verbosity": 1}"#).unwrap();
+/// assert_eq!(cfg.output, OutputMode::JSON);
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct Config {
     /// The output mode.
     ///
@@ -86,7 +534,86 @@ pub struct Config {
     /// The theme to use for the output.
     ///
     /// The theme is not taken into account when not outputing to a TTY.
-    pub theme: HashMap<Level, Style>,
+    ///
+    /// Defaults to the preset named by the `XMT_THEME` environment variable (see [Theme::named](crate::Theme::named)),
+    /// or to [Theme::default](crate::Theme::default).
+    pub theme: Theme,
+
+    /// When to color the output.
+    ///
+    /// Independent of whether the output is a TTY, which decides whether prefixes, padding
+    /// and interactive features are used.
+    pub color: ColorChoice,
+
+    /// The colors supported by the terminal.
+    ///
+    /// Detected from the `COLORTERM` and `TERM` environment variables when not set.
+    /// Colors the terminal does not support are mapped to the closest supported color.
+    pub color_depth: Option<ColorDepth>,
+
+    /// Whether the terminal supports Unicode.
+    ///
+    /// When it does not, prefixes are replaced by their [fallback](crate::Style::fallback_prefix).
+    /// Detected from the `LC_ALL`, `LC_CTYPE` and `LANG` environment variables when not set.
+    pub unicode: Option<bool>,
+
+    /// The format of messages.
+    pub messages: MessageFormat,
+
+    /// The verbosity of the output.
+    ///
+    /// A level is only printed when the verbosity is at least its minimum verbosity.
+    /// Built-in levels are printed at verbosity `0` and above,
+    /// warnings at `-1` and above, and errors and prompts are always printed.
+    pub verbosity: i8,
+
+    /// User-defined levels, by name.
+    pub levels: HashMap<String, CustomLevel>,
+
+    /// Whether prompts are allowed.
+    ///
+    /// When disabled, prompts fail as if stdout was not a TTY.
+    pub interactive: bool,
+
+    /// Whether to collapse consecutive identical messages.
+    ///
+    /// The message is printed once, followed by the number of times it was repeated
+    /// when a different message is printed.
+    pub dedup: bool,
+
+    /// Rate limits of messages, by key.
+    ///
+    /// The key of a message is the value of its `key` [field](crate::Field), or its text if it has none.
+    /// Messages over the limit are not printed, and their number is the `suppressed` field
+    /// of the next message with the same key that is printed, or is printed by [XMT::flush](crate::XMT::flush).
+    pub rate_limits: HashMap<String, RateLimit>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            output: OutputMode::default(),
+            theme: Theme::from_env().unwrap_or_default(),
+            color: ColorChoice::default(),
+            color_depth: None,
+            unicode: None,
+            messages: MessageFormat::default(),
+            verbosity: 0,
+            levels: HashMap::new(),
+            interactive: true,
+            dedup: false,
+            rate_limits: HashMap::new(),
+        }
+    }
+}
+
+/// Overrides loaded from a theme file.
+#[derive(Deserialize)]
+struct ThemeFile {
+    #[serde(flatten)]
+    styles: HashMap<Level, Style>,
+
+    indent: Option<Indent>,
 }
 
 impl Config {
@@ -101,10 +628,160 @@ impl Config {
     /// Config::default().with_style(Level::Normal, Style::new(Color::Red));
     /// ```
     pub fn with_style(mut self, level: Level, style: Style) -> Self {
-        self.theme.insert(level, style);
+        self.theme.styles.insert(level, style);
         self
     }
 
+    /// Set the theme.
+    ///
+    /// Replaces all previously set styles.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use xmt::{Config, Theme};
+    ///
+    /// Config::default().with_theme(Theme::screen_reader());
+    /// ```
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Register a user-defined level.
+    ///
+    /// Messages for the level are emitted with [XMT::log](crate::XMT::log).
+    ///
+    /// # Examples
+    /// ```rust
+    /// use xmt::{Color, Config, CustomLevel, Level, Style, XMT};
+    ///
+    /// let cfg = Config::default().with_level("notice", CustomLevel::new(Style::new(Color::Cyan)));
+    /// XMT::new(cfg).log(Level::custom("notice"), "deployment started");
+    /// ```
+    pub fn with_level(mut self, name: &str, level: CustomLevel) -> Self {
+        self.levels.insert(String::from(name), level);
+        self
+    }
+
+    /// Collapse consecutive identical messages.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use xmt::{Config, XMT};
+    ///
+    /// let xmt = XMT::new(Config::default().with_dedup(true));
+    /// for _ in 0..3 {
+    ///     xmt.warn("retrying");
+    /// }
+    /// xmt.flush();
+    ///
+    /// // Prints:
+    /// // ! retrying
+    /// // ! (repeated 2 times)
+    /// ```
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Limit how often messages with the provided key are printed.
+    ///
+    /// Overwrites any previously set limit for the given key.
+    pub fn with_rate_limit(mut self, key: &str, limit: RateLimit) -> Self {
+        self.rate_limits.insert(String::from(key), limit);
+        self
+    }
+
+    /// Set the verbosity of the output.
+    pub fn with_verbosity(mut self, verbosity: i8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Set when to color the output.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use xmt::{ColorChoice, Config};
+    ///
+    /// // Keep colors when piping to `less -R`.
+    /// Config::default().with_color(ColorChoice::Always);
+    /// ```
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the colors supported by the terminal, disabling detection.
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.color_depth = Some(depth);
+        self
+    }
+
+    /// Set whether the terminal supports Unicode, disabling detection.
+    pub fn with_unicode(mut self, unicode: bool) -> Self {
+        self.unicode = Some(unicode);
+        self
+    }
+
+    /// Enables JSON messages
+    pub fn with_json_messages(mut self) -> Self {
+        self.messages = MessageFormat::JSON;
+        self
+    }
+
+    /// Allow or forbid prompts.
+    pub fn with_interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Enables logfmt messages
+    pub fn with_logfmt_messages(mut self) -> Self {
+        self.messages = MessageFormat::Logfmt;
+        self
+    }
+
+    /// Load a theme file, overriding the style of every level it defines.
+    ///
+    /// The file maps level names to styles, and can set the [indent](crate::Indent) of nested scopes.
+    /// It is parsed as JSON if its extension is `json`,
+    /// and as TOML otherwise. Can be called multiple times to layer themes on top of each other.
+    ///
+    /// # Errors
+    /// Returns an [io::Error](std::io::Error) error if the file could not be read or is not a valid theme.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use xmt::{Color, Config, Level};
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let path = std::env::temp_dir().join("xmt-load-theme-example.toml");
+    /// std::fs::write(&path, "[success]\nprefix = \"ok\"\ncolor = \"bright green\"\n")?;
+    ///
+    /// let cfg = Config::default().load_theme(&path)?;
+    /// assert_eq!(cfg.theme.styles[&Level::Success].color, Color::BrightGreen);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_theme<P: AsRef<Path>>(mut self, path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)?;
+
+        let theme: ThemeFile = if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            serde_json::from_str(&data)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            toml::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        };
+
+        self.theme.styles.extend(theme.styles);
+        if let Some(indent) = theme.indent {
+            self.theme.indent = indent;
+        }
+        Ok(self)
+    }
+
     /// Enables JSON output
     ///
     /// Mutually exclusive with [xmt::Config::with_tree_output](crate::Config::with_tree_output).
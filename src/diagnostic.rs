@@ -0,0 +1,371 @@
+use std::ops::Range;
+
+use serde_json::json;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::{Attributes, Field, Level, XMT};
+
+/// A span of source code, pointed at by a [Diagnostic](crate::Diagnostic).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    /// The byte range of the span in the source.
+    pub span: Range<usize>,
+
+    /// The message printed next to the span.
+    pub message: String,
+
+    /// Whether the span is the cause of the diagnostic.
+    ///
+    /// Primary spans are underlined with `^` in the color of the diagnostic,
+    /// and secondary spans with `-` in the color of normal messages.
+    pub primary: bool,
+}
+
+impl Label {
+    /// A span that is the cause of the diagnostic.
+    pub fn primary(span: Range<usize>, message: &str) -> Self {
+        Self {
+            span,
+            message: String::from(message),
+            primary: true,
+        }
+    }
+
+    /// A span that gives context to the diagnostic.
+    pub fn secondary(span: Range<usize>, message: &str) -> Self {
+        Self {
+            span,
+            message: String::from(message),
+            primary: false,
+        }
+    }
+}
+
+/// A message about a source file, printed with an excerpt of the source.
+///
+/// # Examples
+/// ```rust
+/// use xmt::{Diagnostic, Label, Level, XMT};
+///
+/// let source = "[server]\nport = \"eighty\"\n";
+/// let diagnostic = Diagnostic::new(Level::Error, "invalid port")
+///     .with_source("config.toml", source)
+///     .with_label(Label::primary(16..24, "expected an integer"))
+///     .with_label(Label::secondary(0..8, "in this table"))
+///     .with_note("ports are numbers between 1 and 65535");
+///
+/// let xmt = XMT::default();
+/// xmt.diagnostic(&diagnostic);
+///
+/// // Prints:
+/// // ! invalid port
+/// //   --> config.toml:2:8
+/// //    |
+/// //  1 | [server]
+/// //    | -------- in this table
+/// //  2 | port = "eighty"
+/// //    |        ^^^^^^^^ expected an integer
+/// //    = note: ports are numbers between 1 and 65535
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The level of the diagnostic, giving it its color and stream.
+    pub level: Level,
+    pub message: String,
+
+    /// The name of the source, usually its path.
+    pub name: Option<String>,
+    pub source: String,
+    pub labels: Vec<Label>,
+
+    /// Additional lines printed after the excerpt.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new<L: Into<Level>>(level: L, message: &str) -> Self {
+        Self {
+            level: level.into(),
+            message: String::from(message),
+            name: None,
+            source: String::new(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Set the source the labels point into, along with its name.
+    pub fn with_source(mut self, name: &str, source: &str) -> Self {
+        self.name = Some(String::from(name));
+        self.source = String::from(source);
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(String::from(note));
+        self
+    }
+}
+
+/// The position of a label in the source.
+struct Position<'a> {
+    label: &'a Label,
+
+    /// The index of the line of the label, starting at 0.
+    line: usize,
+
+    /// The column of the start of the label, in characters, starting at 0.
+    column: usize,
+
+    /// The display width of the line before the label.
+    offset: usize,
+
+    /// The display width of the label, clipped to its first line.
+    width: usize,
+}
+
+/// The start of each line of a source, in bytes.
+fn line_starts(source: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+        .collect()
+}
+
+/// The text of a line, without its line ending.
+fn line_text<'a>(source: &'a str, starts: &[usize], line: usize) -> &'a str {
+    let end = starts.get(line + 1).map_or(source.len(), |next| next - 1);
+    source[starts[line]..end].trim_end_matches('\r')
+}
+
+/// Clamp a byte offset to the closest character boundary of the source at or before it.
+fn floor_boundary(source: &str, mut offset: usize) -> usize {
+    offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn locate<'a>(source: &str, starts: &[usize], label: &'a Label) -> Position<'a> {
+    let start = floor_boundary(source, label.span.start);
+    let line = starts.partition_point(|&line_start| line_start <= start) - 1;
+    let text = line_text(source, starts, line);
+
+    let before = &source[starts[line]..start];
+    let end = floor_boundary(source, label.span.end.max(start)).min(starts[line] + text.len());
+    let width = source[start.min(end)..end].width().max(1);
+
+    Position {
+        label,
+        line,
+        column: before.chars().count(),
+        offset: before.width(),
+        width,
+    }
+}
+
+impl XMT {
+    /// Print a diagnostic, with an excerpt of its source.
+    ///
+    /// The lines pointed at by the labels of the diagnostic are printed with line numbers,
+    /// and their spans are underlined, followed by the message of their label.
+    /// If messages are printed as JSON, or if the [output](crate::OutputMode::JSON) is JSON,
+    /// the diagnostic is printed as an event with `source`, `labels` and `notes` fields.
+    /// Warnings and errors are counted even if they are not printed.
+    ///
+    /// See [Diagnostic](crate::Diagnostic) for an example of the output.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::{Config, Diagnostic, Level, XMT};
+    ///
+    /// let xmt = XMT::new(Config::default().with_json_output());
+    /// xmt.diagnostic(&Diagnostic::new(Level::Warn, "unused key"));
+    /// assert_eq!(xmt.warning_count(), 1);
+    /// ```
+    pub fn diagnostic(&self, diagnostic: &Diagnostic) {
+        let starts = line_starts(&diagnostic.source);
+        let mut positions: Vec<Position> = diagnostic
+            .labels
+            .iter()
+            .map(|label| locate(&diagnostic.source, &starts, label))
+            .collect();
+
+        if !self.is_text_messages() || self.is_json_output() {
+            self.diagnostic_event(diagnostic, &positions);
+            return;
+        }
+        self.count(&diagnostic.level);
+        if !self.is_printed(&diagnostic.level) {
+            return;
+        }
+
+        let spec = self.level_spec(&diagnostic.level);
+        let (stream, color) = (spec.stream, spec.style.color);
+        let normal = self.level_spec(&Level::Normal).style.color;
+        let gutter_color =
+            |text: &str| self.paint(stream, text, normal, None, Attributes::default().dim());
+
+        self.print_message(&diagnostic.level, &diagnostic.message, &[]);
+
+        let last_line = positions.iter().map(|pos| pos.line).max();
+        let width = last_line.map_or(0, |line| (line + 1).to_string().len());
+        let blank = " ".repeat(width);
+
+        let location = positions
+            .iter()
+            .find(|pos| pos.label.primary)
+            .or(positions.first())
+            .map(|pos| format!("{}:{}", pos.line + 1, pos.column + 1))
+            .unwrap_or_default();
+        let location = match &diagnostic.name {
+            Some(name) => format!("{name}:{location}"),
+            None => location,
+        };
+        if last_line.is_some() {
+            self.print_raw(
+                stream,
+                &format!("{blank}{} {location}", gutter_color("-->")),
+                color,
+            );
+            self.print_raw(stream, &format!("{blank} {}", gutter_color("|")), color);
+        }
+
+        positions.sort_by_key(|pos| (pos.line, pos.offset));
+        let mut previous_line = None;
+        for pos in &positions {
+            if previous_line != Some(pos.line) {
+                if previous_line.is_some_and(|previous| previous + 1 < pos.line) {
+                    self.print_raw(stream, &gutter_color("..."), color);
+                }
+
+                let number = format!("{:>width$} |", pos.line + 1);
+                let text = line_text(&diagnostic.source, &starts, pos.line);
+                self.print_raw(stream, &format!("{} {text}", gutter_color(&number)), color);
+                previous_line = Some(pos.line);
+            }
+
+            let (marker, marker_color) = if pos.label.primary {
+                ("^", color)
+            } else {
+                ("-", normal)
+            };
+            let underline = format!("{} {}", marker.repeat(pos.width), pos.label.message);
+            let underline = self.paint(
+                stream,
+                underline.trim_end(),
+                marker_color,
+                None,
+                Attributes::default().bold(),
+            );
+            self.print_raw(
+                stream,
+                &format!(
+                    "{blank} {} {}{underline}",
+                    gutter_color("|"),
+                    " ".repeat(pos.offset)
+                ),
+                color,
+            );
+        }
+
+        for note in &diagnostic.notes {
+            self.print_raw(
+                stream,
+                &format!("{blank} {} {note}", gutter_color("= note:")),
+                color,
+            );
+        }
+    }
+
+    /// Print a diagnostic as an event, for structured message formats.
+    fn diagnostic_event(&self, diagnostic: &Diagnostic, positions: &[Position]) {
+        let mut fields = Vec::new();
+        if let Some(name) = &diagnostic.name {
+            fields.push(Field::new("source", name));
+        }
+        if !positions.is_empty() {
+            let labels: Vec<_> = positions
+                .iter()
+                .map(|pos| {
+                    json!({
+                        "start": pos.label.span.start,
+                        "end": pos.label.span.end,
+                        "line": pos.line + 1,
+                        "column": pos.column + 1,
+                        "message": pos.label.message,
+                        "primary": pos.label.primary,
+                    })
+                })
+                .collect();
+            fields.push(Field::new("labels", &labels));
+        }
+        if !diagnostic.notes.is_empty() {
+            fields.push(Field::new("notes", &diagnostic.notes));
+        }
+
+        self.log_event(&diagnostic.level, &diagnostic.message, &fields);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line, column, offset and width of a label.
+    fn position(source: &str, span: Range<usize>) -> (usize, usize, usize, usize) {
+        let label = Label::primary(span, "");
+        let pos = locate(source, &line_starts(source), &label);
+        (pos.line, pos.column, pos.offset, pos.width)
+    }
+
+    #[test]
+    fn locate_multibyte_text() {
+        let source = "é = 1\nnom = \"déjà\"\n";
+        let start = source.find("déjà").unwrap();
+        assert_eq!(position(source, start..start + "déjà".len()), (1, 7, 7, 4));
+
+        // Wide characters count as one column, but take two cells.
+        let source = "名前 = 1\n";
+        let start = source.find('1').unwrap();
+        assert_eq!(position(source, start..start + 1), (0, 5, 7, 1));
+        assert_eq!(position(source, 0..3), (0, 0, 0, 2));
+    }
+
+    #[test]
+    fn locate_span_inside_character() {
+        let source = "x = \"é\"\n";
+        let start = source.find('é').unwrap();
+        assert_eq!(position(source, start + 1..start + 2), (0, 5, 5, 1));
+    }
+
+    #[test]
+    fn locate_span_past_end() {
+        assert_eq!(position("a = 1", 3..100), (0, 3, 3, 2));
+        assert_eq!(position("a = 1", 100..120), (0, 5, 5, 1));
+        assert_eq!(position("a = 1\n", 100..120), (1, 0, 0, 1));
+    }
+
+    #[test]
+    fn locate_multiline_span() {
+        let source = "a = [\n  1,\n]\n";
+        assert_eq!(position(source, 4..source.len()), (0, 4, 4, 1));
+        assert_eq!(position(source, 0..source.len()), (0, 0, 0, 5));
+
+        let source = "key = \"value\"\r\nnext = 1\r\n";
+        assert_eq!(position(source, 6..source.len()), (0, 6, 6, 7));
+    }
+
+    #[test]
+    fn locate_empty_and_reversed_spans() {
+        assert_eq!(position("a = 1\n", 2..2), (0, 2, 2, 1));
+        let reversed = Range { start: 4, end: 1 };
+        assert_eq!(position("a = 1\n", reversed), (0, 4, 4, 1));
+    }
+}
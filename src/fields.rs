@@ -0,0 +1,98 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// A key-value pair attached to a message.
+///
+/// Fields are printed as `key=value` pairs after the message in text and logfmt messages,
+/// and as fields of the object in JSON messages.
+///
+/// # Examples
+/// ```rust
+/// use xmt::{Field, XMT};
+///
+/// let xmt = XMT::default();
+/// xmt.log_fields("normal", "uploaded", &[Field::new("file", "a.txt"), Field::new("bytes", &512)]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub key: String,
+    pub value: Value,
+}
+
+impl Field {
+    /// Create a field from a serializable value.
+    ///
+    /// Numbers and booleans are kept as such in JSON messages.
+    /// If the value can't be represented as JSON, e.g. a path that is not valid UTF-8
+    /// or a map with non-string keys, the field is the text of the serialization error.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use xmt::Field;
+    ///
+    /// let field = Field::new("sizes", &HashMap::from([((1, 2), 3)]));
+    /// assert!(field.value.is_string());
+    /// ```
+    pub fn new<V: Serialize + ?Sized>(key: &str, value: &V) -> Self {
+        Self {
+            key: String::from(key),
+            value: serde_json::to_value(value).unwrap_or_else(|e| Value::String(e.to_string())),
+        }
+    }
+
+    /// Create a field from the [fmt::Display](std::fmt::Display) representation of a value.
+    pub fn display<V: Display + ?Sized>(key: &str, value: &V) -> Self {
+        Self {
+            key: String::from(key),
+            value: Value::String(value.to_string()),
+        }
+    }
+}
+
+/// Format a value as in logfmt, quoting it if it is empty or contains spaces, quotes or `=`.
+fn format_value(value: &Value) -> String {
+    let text = match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    let needs_quotes = text.is_empty()
+        || text
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '=');
+    if needs_quotes {
+        format!("{text:?}")
+    } else {
+        text
+    }
+}
+
+/// Format fields as space-separated logfmt pairs.
+pub(crate) fn to_logfmt<'a, I: IntoIterator<Item = &'a Field>>(fields: I) -> String {
+    fields
+        .into_iter()
+        .map(|field| format!("{}={}", field.key, format_value(&field.value)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Format fields as a JSON object.
+///
+/// Later fields take precedence over earlier fields with the same key,
+/// and `level` and `message` take precedence over all fields.
+pub(crate) fn to_json<'a, I: IntoIterator<Item = &'a Field>>(
+    level: &str,
+    message: &str,
+    fields: I,
+) -> Value {
+    let mut object: Map<String, Value> = fields
+        .into_iter()
+        .map(|field| (field.key.clone(), field.value.clone()))
+        .collect();
+    object.insert(String::from("level"), Value::from(level));
+    object.insert(String::from("message"), Value::from(message));
+    Value::Object(object)
+}
@@ -1,14 +1,61 @@
+use std::cell::{Cell, RefCell};
+use std::error::Error;
 use std::fmt::Display;
-use std::io;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::process::{self, ExitCode};
+use std::time::Instant;
 
 use once_cell::sync::OnceCell;
 
 use parking_lot::Mutex;
 
-use crate::{Config, XMT};
+use crate::collapse::Buffer;
+use crate::{Config, Diagnostic, Failure, Outcome, XMT};
 
 static INSTANCE: OnceCell<Mutex<XMT>> = OnceCell::new();
 
+thread_local! {
+    /// The nesting level of the current thread.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+
+    /// The buffer of the collapsed scope the current thread is in, if any.
+    static BUFFER: RefCell<Option<Buffer>> = const { RefCell::new(None) };
+}
+
+#[cfg(feature = "tokio")]
+tokio::task_local! {
+    /// The nesting level of the current task, within [nest_async].
+    static TASK_DEPTH: Cell<usize>;
+}
+
+/// The nesting level of the current task if within [nest_async], or of the current thread.
+pub(crate) fn depth() -> usize {
+    #[cfg(feature = "tokio")]
+    if let Ok(depth) = TASK_DEPTH.try_with(Cell::get) {
+        return depth;
+    }
+
+    DEPTH.with(Cell::get)
+}
+
+/// The buffer of the collapsed scope the current thread is in, if any.
+pub(crate) fn buffer() -> Option<Buffer> {
+    BUFFER.with(|buffer| buffer.borrow().clone())
+}
+
+/// Set the nesting level of the current task if within [nest_async], or of the current thread.
+///
+/// Returns the previous nesting level.
+pub(crate) fn set_depth(value: usize) -> usize {
+    #[cfg(feature = "tokio")]
+    if let Ok(previous) = TASK_DEPTH.try_with(|depth| depth.replace(value)) {
+        return previous;
+    }
+
+    DEPTH.with(|depth| depth.replace(value))
+}
+
 /// Initialize the global XMT instance with the provided configuration.
 ///
 /// # Example
@@ -37,8 +84,156 @@ pub fn get_instance() -> &'static Mutex<XMT> {
     INSTANCE.get_or_init(|| Mutex::new(XMT::default()))
 }
 
+/// Get a copy of the global XMT instance, nested at the level of the current thread.
+///
+/// The configuration of the global instance is shared by all threads,
+/// but each thread has its own nesting level, starting at the top level.
+/// Futures running within [nest_async] have their own nesting level instead.
+/// The returned instance can be moved to a worker thread to keep the nesting level of the current thread.
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// xmt::nest("Spawning workers", || {
+///     let xmt = xmt::current();
+///     std::thread::spawn(move || xmt.print("Within scope")).join().unwrap();
+///     std::thread::spawn(|| xmt::print!("Outside of scope")).join().unwrap();
+/// });
+/// ```
+pub fn current() -> XMT {
+    let mut xmt = get_instance().lock().at_level(depth());
+    xmt.buffer = buffer();
+    xmt
+}
+
+/// Get a copy of the global XMT instance whose messages carry the provided field.
+///
+/// Unlike [current], the copy is not frozen at the level of the current thread:
+/// its messages are printed at the level and in the collapsed scope of the thread printing them.
+///
+/// See [XMT::with_field](crate::XMT::with_field).
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// let api = xmt::with_field("service", "api");
+/// api.print("Starting");
+/// xmt::nest("Serving", || api.print("Listening"));
+///
+/// // Prints:
+/// // [service=api] Starting
+/// // Serving
+/// //   [service=api] Listening
+/// ```
+pub fn with_field<V: serde::Serialize + ?Sized>(key: &str, value: &V) -> XMT {
+    let mut xmt = current().with_field(key, value);
+    xmt.follows_thread = true;
+    xmt
+}
+
+/// Print an error with the global XMT instance, followed by the [source](std::error::Error::source) chain of the error.
+///
+/// See [XMT::report](crate::XMT::report).
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// if let Err(e) = std::fs::read_to_string("/does/not/exist") {
+///     xmt::report(&e);
+/// }
+/// ```
+pub fn report(error: &dyn Error) {
+    current().report(error)
+}
+
+/// Print a diagnostic with the global XMT instance, with an excerpt of its source.
+///
+/// See [XMT::diagnostic](crate::XMT::diagnostic).
+pub fn diagnostic(diagnostic: &Diagnostic) {
+    current().diagnostic(diagnostic)
+}
+
+/// Guard of a nested scope of the global XMT instance.
+///
+/// Restores the previous nesting level of the current thread when dropped,
+/// including when unwinding from a panic.
+#[must_use = "the scope is closed as soon as the guard is dropped"]
+pub struct NestGuard {
+    depth: usize,
+
+    // The nesting level is tracked per thread, so the guard must be dropped on the thread that created it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for NestGuard {
+    fn drop(&mut self) {
+        current().close_scope();
+        set_depth(self.depth);
+    }
+}
+
+/// Enter a nested scope within the global XMT instance.
+///
+/// The scope lasts until the returned guard is dropped, and only applies to the current thread.
+///
+/// # Example
+/// ```rust
+/// fn deploy() -> std::io::Result<()> {
+///     let _scope = xmt::enter("Deploying");
+///     xmt::print!("Within scope");
+///     std::fs::metadata(".")?;
+///     xmt::print!("Still within scope");
+///     Ok(())
+/// }
+///
+/// xmt::init_default();
+/// deploy().unwrap();
+/// xmt::print!("Outside of scope");
+///
+/// // Prints:
+/// // Deploying
+/// //   Within scope
+/// //   Still within scope
+/// // Outside of scope
+/// ```
+pub fn enter(message: &str) -> NestGuard {
+    current().print(message);
+    open_scope()
+}
+
+/// Enter a nested scope without printing a message.
+fn open_scope() -> NestGuard {
+    let depth = set_depth(depth() + 1);
+
+    NestGuard {
+        depth,
+        _not_send: PhantomData,
+    }
+}
+
+/// Guard of a collapsed scope, restoring the buffer of the enclosing scope when dropped.
+struct BufferGuard {
+    previous: Option<Buffer>,
+}
+
+impl BufferGuard {
+    fn new(buffer: Option<Buffer>) -> Self {
+        let current = BUFFER.with(|cell| cell.borrow().clone());
+        let previous = BUFFER.with(|cell| cell.replace(buffer.or(current)));
+        Self { previous }
+    }
+}
+
+impl Drop for BufferGuard {
+    fn drop(&mut self) {
+        BUFFER.with(|cell| cell.replace(self.previous.take()));
+    }
+}
+
 /// Execute the provided closure in a nested scope within the global XMT instance.
 ///
+/// The scope is closed even if the closure panics.
+///
 /// # Example
 /// ```rust
 /// xmt::init_default();
@@ -52,34 +247,228 @@ pub fn get_instance() -> &'static Mutex<XMT> {
 /// // Begin nested scope
 /// //   Within scope
 /// ```
-#[allow(unused_must_use)]
 pub fn nest<T, F: FnOnce() -> T>(message: &str, func: F) -> T {
-    let mtx = get_instance();
+    let _scope = enter(message);
+    func()
+}
 
-    let orig = {
-        let mut guard = mtx.lock();
-        let orig = guard.clone();
-        orig.print(message);
+/// Execute the provided closure in a nested scope within the global XMT instance,
+/// and report how long it took when it closes.
+///
+/// Prints a success line with the elapsed time, or an error line if the closure returned an `Err`.
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// let res: std::io::Result<()> = xmt::nest_timed("Building", || {
+///     xmt::print!("Compiling");
+///     Ok(())
+/// });
+///
+/// // Prints:
+/// // Building
+/// //   Compiling
+/// // ✔ Building (12ms)
+/// ```
+pub fn nest_timed<T: Outcome, F: FnOnce() -> T>(message: &str, func: F) -> T {
+    let start = Instant::now();
+    let ret_val = nest(message, func);
+
+    current().report_timing(message, start.elapsed(), ret_val.is_failure());
+    ret_val
+}
+
+/// Execute the provided closure in a nested scope within the global XMT instance,
+/// and report its result when it closes.
+///
+/// Prints a success line if the closure returned `Ok`, or an error line followed by
+/// the [source](std::error::Error::source) chain of the error if it returned `Err`.
+/// The result is returned unchanged.
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// let config = xmt::nest_result("Reading config", || {
+///     xmt::print!("Opening file");
+///     std::fs::read_to_string("/does/not/exist")
+/// });
+/// assert!(config.is_err());
+///
+/// // Prints:
+/// // Reading config
+/// //   Opening file
+/// // ! Reading config: No such file or directory (os error 2)
+/// ```
+pub fn nest_result<T, K, E: Failure<K>, F: FnOnce() -> Result<T, E>>(
+    message: &str,
+    func: F,
+) -> Result<T, E> {
+    let ret_val = nest(message, func);
+
+    current().report_result(message, &ret_val);
+    ret_val
+}
 
-        let nested = orig.nest();
-        *guard = nested;
-        orig
+/// Execute the provided closure in a collapsed scope within the global XMT instance.
+///
+/// The output of the scope is buffered. If the closure succeeds, the scope collapses into a single success line.
+/// If it returned an `Err` or panicked, the buffered output is replayed with its indentation, followed by an error line
+/// unless it panicked. The output is also replayed, before the success line, if a warning or an error was printed in the scope.
+///
+/// Output is not buffered when stdout is not a TTY or when messages are printed as JSON.
+/// Output printed from other threads with the macros is not buffered.
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// let res: std::io::Result<()> = xmt::nest_collapsed("Building", || {
+///     xmt::print!("Compiling");
+///     Err(std::io::Error::new(std::io::ErrorKind::Other, "linker not found"))
+/// });
+///
+/// // Prints:
+/// // Building
+/// //   Compiling
+/// // ! Building failed
+/// ```
+pub fn nest_collapsed<T: Outcome, F: FnOnce() -> T>(message: &str, func: F) -> T {
+    let xmt = current();
+    let collapsed = xmt.open_collapsed(message);
+
+    let ret_val = {
+        let _buffer = BufferGuard::new(collapsed.buffer());
+        let _scope = open_scope();
+        func()
     };
 
-    let ret_val = func();
+    collapsed.close(ret_val.is_failure());
+    ret_val
+}
+
+/// Print the slowest scopes timed with [nest_timed], slowest first.
+///
+/// Meant to be called before exiting.
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// xmt::nest_timed("Building", || ());
+/// xmt::print_slowest_scopes(5);
+/// ```
+pub fn print_slowest_scopes(count: usize) {
+    current().print_slowest_scopes(count)
+}
 
-    {
-        let mut guard = mtx.lock();
-        *guard = orig;
+/// Print the number of warnings and errors printed with the global XMT instance.
+///
+/// Meant to be called before exiting.
+///
+/// # Example
+/// ```rust
+/// xmt::init_default();
+/// xmt::warn!("deprecated key");
+/// xmt::summary();
+///
+/// // Prints:
+/// // ! deprecated key
+/// // ! Finished with 1 warning
+/// ```
+pub fn summary() {
+    current().summary()
+}
+
+/// The exit code of the program: failure if any error was printed with the global XMT instance, success otherwise.
+///
+/// # Example
+/// ```rust
+/// use std::process::ExitCode;
+///
+/// fn main() -> ExitCode {
+///     xmt::init_default();
+///     for key in ["name", "version"] {
+///         xmt::print!("Checked {key}");
+///     }
+///     xmt::exit_code()
+/// }
+/// ```
+pub fn exit_code() -> ExitCode {
+    current().exit_code()
+}
+
+/// Exit the process with a failure code if any error was printed with the global XMT instance.
+///
+/// Output is flushed before exiting, including the number of times the last message was repeated
+/// and the number of messages suppressed by rate limits.
+pub fn exit_if_errors() {
+    let xmt = current();
+    if xmt.error_count() > 0 {
+        xmt.flush();
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        process::exit(1);
     }
+}
+
+/// Run the provided future in a nested scope within the global XMT instance.
+///
+/// The nesting level follows the future across `.await` points, whichever thread it is polled on.
+/// Tasks spawned from within the future start at the top level.
+///
+/// # Example
+/// ```rust
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// xmt::init_default();
+/// xmt::nest_async("Downloading", async {
+///     tokio::task::yield_now().await;
+///     xmt::print!("Within scope");
+/// })
+/// .await;
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn nest_async<F: std::future::Future>(message: &str, future: F) -> F::Output {
+    let xmt = current();
+    xmt.print(message);
+
+    let nested = xmt.nest();
+    let ret_val = TASK_DEPTH.scope(Cell::new(depth() + 1), future).await;
+    nested.close_scope();
 
     ret_val
 }
 
+/// Prompt the user for input without blocking the executor.
+///
+/// # Errors
+/// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
+#[cfg(feature = "tokio")]
+pub async fn prompt_async(msg: &str) -> io::Result<String> {
+    current().prompt_async(msg).await
+}
+
+/// Prompt the user for a yes/no answer without blocking the executor.
+///
+/// # Errors
+/// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
+#[cfg(feature = "tokio")]
+pub async fn prompt_yn_async(msg: &str, default: bool) -> io::Result<bool> {
+    current().prompt_yn_async(msg, default).await
+}
+
+/// Prompt the user to select an item from a list without blocking the executor.
+///
+/// # Errors
+/// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
+#[cfg(feature = "tokio")]
+pub async fn pick_async<'a, E: Display>(msg: &str, items: &'a [E]) -> io::Result<&'a E> {
+    current().pick_async(msg, items).await
+}
+
 /// Prompt the user to select an item from a list.
 ///
 /// # Errors
-/// Returns an [io::Error](std::io::Error) error if stdout is not a TTY or if reading from stdin failed.
+/// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
 ///
 /// # Example
 /// ```no_run
@@ -96,6 +485,5 @@ pub fn nest<T, F: FnOnce() -> T>(message: &str, func: F) -> T {
 /// # Returns
 /// A reference to the item selected by the user.
 pub fn pick<'a, E: Display>(msg: &str, items: &'a [E]) -> io::Result<&'a E> {
-    let mtx = get_instance();
-    mtx.lock().pick(msg, items)
+    current().pick(msg, items)
 }
@@ -1,13 +1,52 @@
 //! Batteries-included CLI output library for Rust.
 
+#[cfg(feature = "clap")]
+pub mod cli;
 #[doc(hidden)]
 pub mod global;
 
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod collapse;
+mod color;
 mod config;
+mod diagnostic;
+mod fields;
+#[cfg(feature = "log")]
+mod log_bridge;
 mod macros;
+mod panic;
+mod report;
+mod run;
+mod summary;
+mod theme;
+mod throttle;
+mod timing;
+#[cfg(feature = "tracing")]
+mod tracing_layer;
 mod xmt;
 
 pub use crate::xmt::XMT;
-pub use colored::Color;
-pub use config::{Config, Level, OutputMode, Style};
-pub use global::{init, init_default, nest, pick};
+pub use color::{Color, ParseColorError};
+pub use config::{
+    Attributes, ColorChoice, ColorDepth, Config, CustomLevel, Level, MessageFormat, OutputMode,
+    PrefixStyle, RateLimit, Stream, Style,
+};
+pub use diagnostic::{Diagnostic, Label};
+pub use fields::Field;
+pub use global::{
+    current, diagnostic, enter, exit_code, exit_if_errors, init, init_default, nest,
+    nest_collapsed, nest_result, nest_timed, pick, print_slowest_scopes, report, summary,
+    with_field, NestGuard,
+};
+#[cfg(feature = "tokio")]
+pub use global::{nest_async, pick_async, prompt_async, prompt_yn_async};
+#[cfg(feature = "log")]
+pub use log_bridge::LogBridge;
+pub use panic::install_panic_hook;
+pub use report::Report;
+pub use run::{run, Failure};
+pub use theme::{Indent, IndentStyle, Theme};
+pub use timing::Outcome;
+#[cfg(feature = "tracing")]
+pub use tracing_layer::TracingLayer;
@@ -0,0 +1,122 @@
+use std::io::{self, Write};
+
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::Level;
+
+/// A backend for the [log](https://docs.rs/log) crate, printing records with the global XMT instance.
+///
+/// Records are printed at the nesting level of the current thread, with their target as a tag.
+/// Errors and warnings are printed as such, info records as [Level::Normal](crate::Level::Normal),
+/// and debug and trace records as [Level::Detail](crate::Level::Detail).
+///
+/// # Example
+/// ```rust
+/// use log::LevelFilter;
+/// use xmt::LogBridge;
+///
+/// xmt::init_default();
+/// LogBridge::new()
+///     .with_level(LevelFilter::Debug)
+///     .with_module_level("hyper", LevelFilter::Warn)
+///     .init()
+///     .unwrap();
+///
+/// xmt::nest("Connecting", || log::info!("resolved host"));
+///
+/// // Prints:
+/// // Connecting
+/// //   [target=my_app] resolved host
+/// ```
+pub struct LogBridge {
+    level: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LogBridge {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            modules: Vec::new(),
+        }
+    }
+}
+
+impl LogBridge {
+    /// Create a bridge printing records at the info level and above.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum level of records.
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Set the minimum level of records from a module and its submodules.
+    ///
+    /// Overrides the minimum level set with [LogBridge::with_level](crate::LogBridge::with_level).
+    /// When several modules match a target, the most specific one is used.
+    pub fn with_module_level(mut self, module: &str, level: LevelFilter) -> Self {
+        self.modules.push((String::from(module), level));
+        self
+    }
+
+    /// Install the bridge as the logger of the [log](https://docs.rs/log) crate.
+    ///
+    /// # Errors
+    /// Returns a [SetLoggerError](log::SetLoggerError) if a logger was already installed.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let max_level = self
+            .modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, Ord::max);
+
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    /// The minimum level of records from a target.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || target
+                        .strip_prefix(module.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.level, |(_, level)| *level)
+    }
+}
+
+impl Log for LogBridge {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let level = match record.level() {
+            log::Level::Error => Level::Error,
+            log::Level::Warn => Level::Warn,
+            log::Level::Info => Level::Normal,
+            log::Level::Debug | log::Level::Trace => Level::Detail,
+        };
+
+        crate::current()
+            .with_field("target", record.target())
+            .log(level, &record.args().to_string());
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+    }
+}
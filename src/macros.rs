@@ -1,8 +1,44 @@
+/// Splits the arguments of a message macro into the message and its fields, and prints it with the global instance.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __emit {
+    ($level:expr, $fmt:literal $(, $arg:expr)* $(,)? ; $($fields:tt)*) => {
+        $crate::global::current().log_fields($level, &format!($fmt $(, $arg)*), &$crate::__fields!([] $($fields)*))
+    };
+    ($level:expr, $($arg:tt)*) => {
+        $crate::global::current().log_fields($level, &format!($($arg)*), &[])
+    };
+}
+
+/// Builds an array of [Field](crate::Field) from `key = value` pairs.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fields {
+    ([$($out:expr,)*]) => {
+        [$($out),*]
+    };
+    ([$($out:expr,)*] $key:ident = %$value:expr $(, $($rest:tt)*)?) => {
+        $crate::__fields!([$($out,)* $crate::Field::display(stringify!($key), &$value),] $($($rest)*)?)
+    };
+    ([$($out:expr,)*] $key:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__fields!([$($out,)* $crate::Field::new(stringify!($key), &$value),] $($($rest)*)?)
+    };
+}
+
 /// Forwards to the [XMT::print](crate::XMT::print) method of the global instance.
+///
+/// Key-value fields can follow the message after a `;`, as with [XMT::log_fields](crate::XMT::log_fields).
+/// Values are serialized, or formatted with [fmt::Display](std::fmt::Display) when preceded by `%`.
+///
+/// # Example
+/// ```rust
+/// let path = std::path::Path::new("a.txt");
+/// xmt::print!("uploaded {}", path.display(); file = path, bytes = 512, took = %"3s");
+/// ```
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => {
-        $crate::global::get_instance().lock().print(&format!($($arg)*));
+        $crate::__emit!($crate::Level::Normal, $($arg)*);
     }
 }
 
@@ -10,7 +46,7 @@ macro_rules! print {
 #[macro_export]
 macro_rules! detail {
     ($($arg:tt)*) => {
-        $crate::global::get_instance().lock().detail(&format!($($arg)*));
+        $crate::__emit!($crate::Level::Detail, $($arg)*);
     }
 }
 
@@ -18,7 +54,7 @@ macro_rules! detail {
 #[macro_export]
 macro_rules! success {
     ($($arg:tt)*) => {
-        $crate::global::get_instance().lock().success(&format!($($arg)*));
+        $crate::__emit!($crate::Level::Success, $($arg)*);
     }
 }
 
@@ -26,7 +62,7 @@ macro_rules! success {
 #[macro_export]
 macro_rules! warn {
     ($($arg:tt)*) => {
-        $crate::global::get_instance().lock().warn(&format!($($arg)*));
+        $crate::__emit!($crate::Level::Warn, $($arg)*);
     }
 }
 
@@ -34,7 +70,15 @@ macro_rules! warn {
 #[macro_export]
 macro_rules! error {
     ($($arg:tt)*) => {
-        $crate::global::get_instance().lock().error(&format!($($arg)*));
+        $crate::__emit!($crate::Level::Error, $($arg)*);
+    }
+}
+
+/// Forwards to the [XMT::log](crate::XMT::log) method of the global instance.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::__emit!($level, $($arg)*);
     }
 }
 
@@ -42,7 +86,7 @@ macro_rules! error {
 #[macro_export]
 macro_rules! out {
     ($arg: expr) => {
-        $crate::global::get_instance().lock().out($arg);
+        $crate::global::current().out($arg);
     };
 }
 
@@ -50,7 +94,7 @@ macro_rules! out {
 #[macro_export]
 macro_rules! prompt {
     ($($arg:tt)*) => {
-        $crate::global::get_instance().lock().prompt(&format!($($arg)*))
+        $crate::global::current().prompt(&format!($($arg)*))
     }
 }
 
@@ -58,6 +102,6 @@ macro_rules! prompt {
 #[macro_export]
 macro_rules! prompt_yn {
     ($($arg:tt)*) => {
-        $crate::global::get_instance().lock().prompt_yn(&format!($($arg)*), false)
+        $crate::global::current().prompt_yn(&format!($($arg)*), false)
     }
 }
@@ -0,0 +1,91 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::thread;
+use std::time::Duration;
+
+use crate::{collapse, global, Field, Level, XMT};
+
+/// Escape sequences resetting the terminal: attributes are cleared and the cursor is shown.
+const RESET_TERMINAL: &str = "\x1b[0m\x1b[?25h";
+
+/// Replace the panic hook with one reporting panics with the global XMT instance.
+///
+/// The output of the collapsed scope the panic happened in, if any, is printed first.
+/// The terminal is then reset, and the panic message is printed as an error at the top level,
+/// with the location of the panic and the name of the thread as [fields](crate::Field).
+/// A backtrace is printed under the message when enabled by `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`.
+/// If messages are not printed as text, or if the [output](crate::OutputMode::JSON) is JSON,
+/// the panic is printed to stderr as an error event, with the backtrace as its `backtrace` field.
+///
+/// # Example
+/// ```no_run
+/// xmt::init_default();
+/// xmt::install_panic_hook();
+/// panic!("boom");
+///
+/// // Prints:
+/// // ! panicked: boom location=src/main.rs:3:1 thread=main
+/// ```
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(report_panic));
+}
+
+fn report_panic(info: &PanicHookInfo) {
+    // The output of the collapsed scope the panic happened in gives its context, so it is printed first.
+    if let Some(buffer) = global::buffer() {
+        collapse::expand(&buffer);
+    }
+    let _ = io::stdout().flush();
+    if atty::is(atty::Stream::Stderr) {
+        eprint!("{RESET_TERMINAL}");
+    }
+
+    // The panic may have happened while the global instance was locked.
+    let xmt = global::get_instance()
+        .try_lock_for(Duration::from_millis(100))
+        .map(|instance| instance.at_level(0))
+        .unwrap_or_default();
+
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+
+    let mut fields = Vec::new();
+    if let Some(location) = info.location() {
+        fields.push(Field::display("location", location));
+    }
+    if let Some(name) = thread::current().name() {
+        fields.push(Field::new("thread", name));
+    }
+
+    let message = format!("panicked: {message}");
+    let backtrace = Backtrace::capture();
+    let captured = backtrace.status() == BacktraceStatus::Captured;
+
+    // The panic is printed directly, so that it is neither throttled nor hidden by the output mode.
+    xmt.count(&Level::Error);
+    if xmt.is_printed(&Level::Error) {
+        xmt.flush_repeat();
+        xmt.print_message(&Level::Error, &message, &fields);
+        if captured {
+            print_backtrace(&xmt, &backtrace);
+        }
+        return;
+    }
+
+    if captured {
+        fields.push(Field::display("backtrace", &backtrace));
+    }
+    xmt.print_event(&Level::Error, &message, &fields);
+}
+
+fn print_backtrace(xmt: &XMT, backtrace: &Backtrace) {
+    let nested = xmt.nest();
+    for line in backtrace.to_string().lines() {
+        nested.print_note(line);
+    }
+}
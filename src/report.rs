@@ -0,0 +1,116 @@
+use std::error::Error;
+
+use crate::{Field, Level, XMT};
+
+/// An error, along with hints on how to solve it.
+///
+/// # Example
+/// ```rust
+/// use xmt::{Report, XMT};
+///
+/// let err = std::fs::read_to_string("/does/not/exist").unwrap_err();
+///
+/// let xmt = XMT::default();
+/// xmt.report_with(
+///     &Report::new(&err)
+///         .with_hint("the file is created by `init`")
+///         .with_help("run `app init` first"),
+/// );
+///
+/// // Prints:
+/// // ! No such file or directory (os error 2)
+/// //   hint: the file is created by `init`
+/// //   help: run `app init` first
+/// ```
+pub struct Report<'a> {
+    pub error: &'a dyn Error,
+    pub hint: Option<String>,
+    pub help: Option<String>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(error: &'a dyn Error) -> Self {
+        Self {
+            error,
+            hint: None,
+            help: None,
+        }
+    }
+
+    /// Explain the likely cause of the error.
+    pub fn with_hint(mut self, hint: &str) -> Self {
+        self.hint = Some(String::from(hint));
+        self
+    }
+
+    /// Suggest a way to solve the error.
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(String::from(help));
+        self
+    }
+}
+
+impl XMT {
+    /// Print an error, followed by the [source](std::error::Error::source) chain of the error.
+    ///
+    /// Each cause is printed nested under the error.
+    /// If messages are printed as JSON, or if the [output](crate::OutputMode::JSON) is JSON,
+    /// a single error object is printed to stderr, with the causes as its `causes` field.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::XMT;
+    ///
+    /// let err = std::fs::read_to_string("/does/not/exist").unwrap_err();
+    ///
+    /// let xmt = XMT::default();
+    /// xmt.report(&err);
+    /// ```
+    pub fn report(&self, error: &dyn Error) {
+        self.report_with(&Report::new(error));
+    }
+
+    /// Print an error as with [XMT::report](crate::XMT::report), followed by its hint and help.
+    ///
+    /// If messages are printed as JSON, the hint and help are the `hint` and `help` fields of the error object.
+    pub fn report_with(&self, report: &Report) {
+        let message = report.error.to_string();
+
+        if self.is_text_messages() && !self.is_json_output() {
+            self.error_chain(&message, report.error);
+
+            let nested = self.nest();
+            if let Some(hint) = &report.hint {
+                nested.print_note(&format!("hint: {hint}"));
+            }
+            if let Some(help) = &report.help {
+                nested.print_note(&format!("help: {help}"));
+            }
+            return;
+        }
+
+        let mut fields = Vec::new();
+        let causes = causes(report.error);
+        if !causes.is_empty() {
+            fields.push(Field::new("causes", &causes));
+        }
+        if let Some(hint) = &report.hint {
+            fields.push(Field::new("hint", hint));
+        }
+        if let Some(help) = &report.help {
+            fields.push(Field::new("help", help));
+        }
+        self.log_event(&Level::Error, &message, &fields);
+    }
+}
+
+/// The messages of the source chain of an error.
+fn causes(error: &dyn Error) -> Vec<String> {
+    let mut causes = Vec::new();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    causes
+}
@@ -0,0 +1,107 @@
+use std::error::Error;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+/// An error returned by the closure passed to [run](crate::run) or [nest_result](crate::nest_result).
+///
+/// Implemented for errors, for boxed errors, and for `anyhow::Error` and `eyre::Report`
+/// with the `anyhow` and `eyre` features.
+///
+/// The `Kind` parameter keeps the implementation for errors apart from the others,
+/// and is inferred by the compiler.
+///
+/// # Example
+/// ```rust
+/// use xmt::XMT;
+///
+/// let xmt = XMT::default();
+/// let res = xmt.nest_result("Reading config", |_| std::fs::read_to_string("/does/not/exist"));
+/// assert!(res.is_err());
+///
+/// # #[cfg(feature = "anyhow")]
+/// # {
+/// let res = xmt.nest_result("Parsing config", |_| -> anyhow::Result<()> {
+///     anyhow::bail!("missing key")
+/// });
+/// assert!(res.is_err());
+/// # }
+/// ```
+pub trait Failure<Kind = Typed> {
+    /// The error to report.
+    fn as_error(&self) -> &(dyn Error + 'static);
+}
+
+/// The [Failure] kind of types implementing [Error].
+pub enum Typed {}
+
+/// The [Failure] kind of boxed and dynamic errors.
+pub enum Dynamic {}
+
+impl<E: Error + 'static> Failure<Typed> for E {
+    fn as_error(&self) -> &(dyn Error + 'static) {
+        self
+    }
+}
+
+impl Failure<Dynamic> for Box<dyn Error> {
+    fn as_error(&self) -> &(dyn Error + 'static) {
+        self.as_ref()
+    }
+}
+
+impl Failure<Dynamic> for Box<dyn Error + Send + Sync> {
+    fn as_error(&self) -> &(dyn Error + 'static) {
+        self.as_ref()
+    }
+}
+
+#[cfg(feature = "anyhow")]
+impl Failure<Dynamic> for anyhow::Error {
+    fn as_error(&self) -> &(dyn Error + 'static) {
+        self.as_ref()
+    }
+}
+
+#[cfg(feature = "eyre")]
+impl Failure<Dynamic> for eyre::Report {
+    fn as_error(&self) -> &(dyn Error + 'static) {
+        self.as_ref()
+    }
+}
+
+/// Run the body of a program, reporting its error with the global XMT instance if it fails.
+///
+/// The error is printed with [XMT::report](crate::XMT::report), along with its causes.
+/// Output is flushed before returning.
+///
+/// # Returns
+/// The exit code of the program: failure if the closure returned an `Err` or if any error was printed,
+/// success otherwise.
+///
+/// # Example
+/// ```rust
+/// use std::process::ExitCode;
+///
+/// fn main() -> ExitCode {
+///     xmt::init_default();
+///     xmt::run(|| -> Result<(), Box<dyn std::error::Error>> {
+///         let config = std::fs::read_to_string("Cargo.toml")?;
+///         xmt::print!("Read {} bytes", config.len());
+///         Ok(())
+///     })
+/// }
+/// ```
+pub fn run<K, E: Failure<K>, F: FnOnce() -> Result<(), E>>(func: F) -> ExitCode {
+    let exit_code = match func() {
+        Ok(()) => crate::exit_code(),
+        Err(e) => {
+            crate::current().report(e.as_error());
+            ExitCode::FAILURE
+        }
+    };
+
+    crate::current().flush();
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    exit_code
+}
@@ -0,0 +1,96 @@
+use std::process::ExitCode;
+
+use crate::{Field, Level, XMT};
+
+/// Format a count along with its noun, e.g. `1 error` or `3 errors`.
+pub(crate) fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+impl XMT {
+    /// The number of warnings printed so far, including those hidden by the verbosity.
+    pub fn warning_count(&self) -> usize {
+        self.shared.lock().warnings
+    }
+
+    /// The number of errors printed so far.
+    pub fn error_count(&self) -> usize {
+        self.shared.lock().errors
+    }
+
+    /// Print the number of warnings and errors printed so far.
+    ///
+    /// Meant to be called before exiting. Output is [flushed](crate::XMT::flush) first,
+    /// so that pending repeat and suppressed counts are printed before the summary.
+    /// If messages are printed as JSON, the counts are the `warnings` and `errors` fields of the event.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::XMT;
+    ///
+    /// let xmt = XMT::default();
+    /// xmt.warn("deprecated key");
+    /// xmt.error("missing key");
+    /// xmt.summary();
+    ///
+    /// // Prints:
+    /// // ! deprecated key
+    /// // ! missing key
+    /// // ! Finished with 1 warning, 1 error
+    /// ```
+    pub fn summary(&self) {
+        self.flush();
+        let (warnings, errors) = (self.warning_count(), self.error_count());
+
+        let mut counts = Vec::new();
+        if warnings > 0 {
+            counts.push(plural(warnings, "warning"));
+        }
+        if errors > 0 {
+            counts.push(plural(errors, "error"));
+        }
+
+        let (level, message) = match (warnings, errors) {
+            (0, 0) => (Level::Success, String::from("Finished")),
+            (_, 0) => (Level::Warn, format!("Finished with {}", counts.join(", "))),
+            _ => (Level::Error, format!("Finished with {}", counts.join(", "))),
+        };
+
+        let fields = if self.is_text_messages() {
+            Vec::new()
+        } else {
+            vec![
+                Field::new("warnings", &warnings),
+                Field::new("errors", &errors),
+            ]
+        };
+        self.print_message(&level, &message, &fields);
+    }
+
+    /// The exit code of the program: failure if any error was printed, success otherwise.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::process::ExitCode;
+    /// use xmt::XMT;
+    ///
+    /// fn main() -> ExitCode {
+    ///     let xmt = XMT::default();
+    ///     for key in ["name", "version"] {
+    ///         xmt.print(&format!("Checked {key}"));
+    ///     }
+    ///     xmt.exit_code()
+    /// }
+    /// ```
+    pub fn exit_code(&self) -> ExitCode {
+        if self.error_count() > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::env;
+
+use serde::Deserialize;
+
+use crate::{Color, Level, Style};
+
+/// The environment variable used to select a preset.
+const THEME_ENV_VAR: &str = "XMT_THEME";
+
+/// The guides drawn before nested messages, once per nesting level.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    /// Plain spaces.
+    Spaces,
+
+    /// ASCII pipes (`|`), with a `` ` `` corner.
+    ///
+    /// Default.
+    #[default]
+    Pipes,
+
+    /// Unicode box-drawing guides (`│`, `├─`), with a `└─` corner.
+    ///
+    /// Falls back to [IndentStyle::Pipes](crate::IndentStyle::Pipes) when the terminal does not support Unicode.
+    Guides,
+
+    /// A custom string.
+    Custom(String),
+}
+
+/// The padding of nested scopes.
+///
+/// # Examples
+/// ```rust
+/// use xmt::{Color, Config, Indent, IndentStyle, Theme};
+///
+/// let indent = Indent::new(IndentStyle::Guides)
+///     .with_colors(vec![Color::Blue, Color::Magenta])
+///     .with_mark_last(true);
+/// Config::default().with_theme(Theme::default().with_indent(indent));
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Indent {
+    /// The guides drawn for each nesting level.
+    pub style: IndentStyle,
+
+    /// The colors of the guides, cycled through by depth.
+    ///
+    /// When empty, guides have the color of the message they precede.
+    pub colors: Vec<Color>,
+
+    /// Whether to mark the last message of a scope with a corner when the scope closes.
+    ///
+    /// Only applies to [IndentStyle::Pipes](crate::IndentStyle::Pipes) and [IndentStyle::Guides](crate::IndentStyle::Guides),
+    /// and to scopes opened with [xmt::nest](crate::nest) or [xmt::enter](crate::enter).
+    /// The message is redrawn in place, so this has no effect on streams that are not TTYs.
+    pub mark_last: bool,
+}
+
+impl Indent {
+    pub fn new(style: IndentStyle) -> Self {
+        Self {
+            style,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn with_mark_last(mut self, mark_last: bool) -> Self {
+        self.mark_last = mark_last;
+        self
+    }
+
+    /// The guide of a nesting level.
+    ///
+    /// `innermost` is set for the level closest to the message, and `last` when the message is the last of its scope.
+    pub(crate) fn segment(&self, unicode: bool, innermost: bool, last: bool) -> &str {
+        match &self.style {
+            IndentStyle::Spaces => "    ",
+            IndentStyle::Guides if unicode => match (innermost, last) {
+                (false, _) => "│   ",
+                (true, false) => "├── ",
+                (true, true) => "└── ",
+            },
+            IndentStyle::Pipes | IndentStyle::Guides => {
+                if innermost && last {
+                    "`   "
+                } else {
+                    "|   "
+                }
+            }
+            IndentStyle::Custom(indent) => indent,
+        }
+    }
+}
+
+/// The styles of the different levels, along with the padding of nested scopes.
+///
+/// Levels missing from a theme use their style from [Theme::default](crate::Theme::default).
+///
+/// # Examples
+/// ```rust
+/// use xmt::{Config, Theme};
+///
+/// Config::default().with_theme(Theme::ascii());
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct Theme {
+    /// The style of each level.
+    #[serde(flatten)]
+    pub styles: HashMap<Level, Style>,
+
+    /// The padding of nested scopes.
+    #[serde(default)]
+    pub indent: Indent,
+}
+
+impl Default for Theme {
+    /// The default theme.
+    fn default() -> Self {
+        Self::new(Indent::default())
+            .with_style(
+                Level::Normal,
+                Style::new(Color::White).with_prefix(String::from("+")),
+            )
+            .with_style(
+                Level::Prompt,
+                Style::new(Color::White).with_prefix(String::from("+")),
+            )
+            .with_style(
+                Level::Success,
+                Style::new(Color::Green)
+                    .with_prefix(String::from("✔"))
+                    .with_fallback_prefix(String::from("*")),
+            )
+            .with_style(
+                Level::Detail,
+                Style::new(Color::White).with_prefix(String::from("+")),
+            )
+            .with_style(
+                Level::Warn,
+                Style::new(Color::Yellow).with_prefix(String::from("!")),
+            )
+            .with_style(
+                Level::Error,
+                Style::new(Color::Red).with_prefix(String::from("!")),
+            )
+    }
+}
+
+impl Theme {
+    /// Create a theme with no styles.
+    pub fn new(indent: Indent) -> Self {
+        Self {
+            styles: HashMap::new(),
+            indent,
+        }
+    }
+
+    /// Set the style for a given level.
+    ///
+    /// Overwrites any previously set style for the given level.
+    pub fn with_style(mut self, level: Level, style: Style) -> Self {
+        self.styles.insert(level, style);
+        self
+    }
+
+    /// Set the padding of nested scopes.
+    pub fn with_indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// A theme with colors and no prefixes.
+    pub fn minimal() -> Self {
+        Self::new(Indent::new(IndentStyle::Spaces))
+            .with_style(Level::Normal, Style::new(Color::White))
+            .with_style(Level::Prompt, Style::new(Color::White))
+            .with_style(Level::Success, Style::new(Color::Green))
+            .with_style(Level::Detail, Style::new(Color::White))
+            .with_style(Level::Warn, Style::new(Color::Yellow))
+            .with_style(Level::Error, Style::new(Color::Red))
+    }
+
+    /// The default theme, using only ASCII characters.
+    pub fn ascii() -> Self {
+        Self::default().with_style(
+            Level::Success,
+            Style::new(Color::Green).with_prefix(String::from("*")),
+        )
+    }
+
+    /// A theme for screen readers.
+    ///
+    /// Prefixes are words instead of glyphs, and nested scopes are not padded.
+    pub fn screen_reader() -> Self {
+        Self::new(Indent::new(IndentStyle::Custom(String::new())))
+            .with_style(Level::Normal, Style::new(Color::White))
+            .with_style(
+                Level::Prompt,
+                Style::new(Color::White).with_prefix(String::from("Question:")),
+            )
+            .with_style(
+                Level::Success,
+                Style::new(Color::Green).with_prefix(String::from("Success:")),
+            )
+            .with_style(Level::Detail, Style::new(Color::White))
+            .with_style(
+                Level::Warn,
+                Style::new(Color::Yellow).with_prefix(String::from("Warning:")),
+            )
+            .with_style(
+                Level::Error,
+                Style::new(Color::Red).with_prefix(String::from("Error:")),
+            )
+    }
+
+    /// Get a preset by name.
+    ///
+    /// Supported names are `default`, `minimal`, `ascii` and `screen-reader`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use xmt::Theme;
+    ///
+    /// assert_eq!(Theme::named("ascii"), Some(Theme::ascii()));
+    /// assert_eq!(Theme::named("nope"), None);
+    /// ```
+    pub fn named(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace('_', "-").as_ref() {
+            "default" => Some(Self::default()),
+            "minimal" => Some(Self::minimal()),
+            "ascii" => Some(Self::ascii()),
+            "screen-reader" => Some(Self::screen_reader()),
+            _ => None,
+        }
+    }
+
+    /// Get the preset named by the `XMT_THEME` environment variable, if any.
+    pub fn from_env() -> Option<Self> {
+        env::var(THEME_ENV_VAR)
+            .ok()
+            .and_then(|name| Self::named(&name))
+    }
+}
+
+/// Whether the locale supports Unicode, from the first of `LC_ALL`, `LC_CTYPE` and `LANG` that is set.
+///
+/// Assumes Unicode support when no locale is set.
+pub(crate) fn detect_unicode() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty());
+
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_uppercase();
+            locale.contains("UTF-8") || locale.contains("UTF8")
+        }
+        None => true,
+    }
+}
@@ -0,0 +1,306 @@
+use std::time::Instant;
+
+use crate::collapse::Buffer;
+use crate::summary::plural;
+use crate::{Field, Level, XMT};
+
+/// The last message printed, along with the number of identical messages that followed it.
+pub(crate) struct Repeat {
+    level: Level,
+    message: String,
+    fields: Vec<Field>,
+    depth: usize,
+    context: Vec<Field>,
+    buffer: Option<Buffer>,
+    count: usize,
+}
+
+impl Repeat {
+    fn matches(&self, xmt: &XMT, level: &Level, message: &str, fields: &[Field]) -> bool {
+        self.level == *level
+            && self.message == message
+            && self.fields == fields
+            && self.depth == xmt.depth()
+            && self.context == xmt.context
+    }
+}
+
+/// The messages printed for a rate-limited key during the current period.
+pub(crate) struct Window {
+    start: Instant,
+    printed: usize,
+    suppressed: usize,
+
+    /// The last message suppressed, printed along with the number of suppressed messages when flushed.
+    last: Option<(Level, String, Vec<Field>)>,
+}
+
+/// The key of a message, used for rate limiting.
+fn message_key<'a>(message: &'a str, fields: &'a [Field]) -> &'a str {
+    fields
+        .iter()
+        .rev()
+        .find(|field| field.key == "key")
+        .and_then(|field| field.value.as_str())
+        .unwrap_or(message)
+}
+
+impl XMT {
+    /// Whether a message should be printed, according to the rate limits and deduplication of the configuration.
+    ///
+    /// Returns `None` if the message is dropped, or the number of messages with the same key
+    /// that were dropped before it.
+    pub(crate) fn throttle(&self, level: &Level, message: &str, fields: &[Field]) -> Option<usize> {
+        let mut suppressed = 0;
+
+        let key = message_key(message, fields);
+        if let Some(limit) = self.cfg.rate_limits.get(key) {
+            let mut shared = self.shared.lock();
+            let now = Instant::now();
+            let window = shared
+                .windows
+                .entry(String::from(key))
+                .or_insert_with(|| Window {
+                    start: now,
+                    printed: 0,
+                    suppressed: 0,
+                    last: None,
+                });
+
+            if now.duration_since(window.start) >= limit.period {
+                window.start = now;
+                window.printed = 0;
+            }
+            if window.printed >= limit.max {
+                window.suppressed += 1;
+                window.last = Some((level.clone(), String::from(message), fields.to_vec()));
+                return None;
+            }
+            window.printed += 1;
+            window.last = None;
+            suppressed = std::mem::take(&mut window.suppressed);
+        }
+
+        if self.cfg.dedup {
+            let mut shared = self.shared.lock();
+            if let Some(repeat) = shared.repeat.as_mut() {
+                if repeat.matches(self, level, message, fields) {
+                    repeat.count += 1;
+                    return None;
+                }
+            }
+
+            let previous = shared.repeat.replace(Repeat {
+                level: level.clone(),
+                message: String::from(message),
+                fields: fields.to_vec(),
+                depth: self.depth(),
+                context: self.context.clone(),
+                buffer: self.buffer(),
+                count: 0,
+            });
+            drop(shared);
+            self.print_repeat(previous);
+        }
+
+        Some(suppressed)
+    }
+
+    /// Print the number of times the last message was repeated, and the number of messages suppressed by rate limits.
+    ///
+    /// Only applies when [deduplication](crate::Config::dedup) or [rate limits](crate::Config::rate_limits) are enabled.
+    /// Called by [XMT::summary](crate::XMT::summary).
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::time::Duration;
+    /// use xmt::{Config, RateLimit, XMT};
+    ///
+    /// let cfg = Config::default().with_rate_limit("retrying", RateLimit::new(1, Duration::from_secs(60)));
+    /// let xmt = XMT::new(cfg);
+    /// for _ in 0..3 {
+    ///     xmt.warn("retrying");
+    /// }
+    /// xmt.flush();
+    ///
+    /// // Prints:
+    /// // ! retrying
+    /// // ! (2 messages suppressed: retrying)
+    /// ```
+    pub fn flush(&self) {
+        self.flush_repeat();
+        self.flush_suppressed();
+    }
+
+    /// Print the number of times the last message was repeated, if it was.
+    ///
+    /// Called automatically before printing a different message.
+    pub(crate) fn flush_repeat(&self) {
+        let repeat = self.shared.lock().repeat.take();
+        self.print_repeat(repeat);
+    }
+
+    /// Print the number of messages suppressed by each rate limit since a message with the same key was printed.
+    fn flush_suppressed(&self) {
+        let mut pending: Vec<_> = self
+            .shared
+            .lock()
+            .windows
+            .iter_mut()
+            .filter(|(_, window)| window.suppressed > 0)
+            .filter_map(|(key, window)| {
+                let suppressed = std::mem::take(&mut window.suppressed);
+                window
+                    .last
+                    .take()
+                    .map(|last| (key.clone(), suppressed, last))
+            })
+            .collect();
+        pending.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (key, suppressed, (level, message, mut fields)) in pending {
+            if self.is_text_messages() {
+                let message = format!("({} suppressed: {key})", plural(suppressed, "message"));
+                self.print_message(&level, &message, &[]);
+            } else {
+                fields.push(Field::new("suppressed", &suppressed));
+                self.print_message(&level, &message, &fields);
+            }
+        }
+    }
+
+    fn print_repeat(&self, repeat: Option<Repeat>) {
+        let Some(repeat) = repeat.filter(|repeat| repeat.count > 0) else {
+            return;
+        };
+
+        let mut xmt = self.at_level(repeat.depth);
+        xmt.context = repeat.context;
+        xmt.buffer = repeat.buffer;
+
+        if xmt.is_text_messages() {
+            let times = if repeat.count == 1 { "time" } else { "times" };
+            let message = format!("(repeated {} {times})", repeat.count);
+            xmt.print_message(&repeat.level, &message, &[]);
+        } else {
+            let mut fields = repeat.fields;
+            fields.push(Field::new("repeated", &repeat.count));
+            xmt.print_message(&repeat.level, &repeat.message, &fields);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Config, RateLimit};
+
+    fn rate_limited(max: usize) -> XMT {
+        XMT::new(
+            Config::default()
+                .with_rate_limit("retrying", RateLimit::new(max, Duration::from_secs(60))),
+        )
+    }
+
+    /// Move the start of the window of a key past the end of its period.
+    fn expire(xmt: &XMT, key: &str) {
+        let mut shared = xmt.shared.lock();
+        let window = shared.windows.get_mut(key).unwrap();
+        window.start = window.start.checked_sub(Duration::from_secs(61)).unwrap();
+    }
+
+    #[test]
+    fn message_keys() {
+        assert_eq!(message_key("retrying", &[]), "retrying");
+        let fields = [Field::new("key", "slow-request"), Field::new("took", &3)];
+        assert_eq!(message_key("took 3s", &fields), "slow-request");
+        assert_eq!(message_key("took 3s", &[Field::new("key", &3)]), "took 3s");
+    }
+
+    #[test]
+    fn rate_limit_resets_after_period() {
+        let xmt = rate_limited(2);
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), Some(0));
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), Some(0));
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), None);
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), None);
+        assert_eq!(xmt.throttle(&Level::Warn, "other", &[]), Some(0));
+
+        expire(&xmt, "retrying");
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), Some(2));
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), Some(0));
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), None);
+    }
+
+    #[test]
+    fn rate_limit_uses_key_field() {
+        let xmt = rate_limited(1);
+        let fields = [Field::new("key", "retrying")];
+        assert_eq!(xmt.throttle(&Level::Warn, "attempt 1", &fields), Some(0));
+        assert_eq!(xmt.throttle(&Level::Warn, "attempt 2", &fields), None);
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), None);
+    }
+
+    #[test]
+    fn flush_takes_suppressed_counts() {
+        let xmt = rate_limited(1);
+        for _ in 0..3 {
+            xmt.throttle(&Level::Warn, "retrying", &[]);
+        }
+        {
+            let shared = xmt.shared.lock();
+            let window = &shared.windows["retrying"];
+            assert_eq!(window.suppressed, 2);
+            assert_eq!(
+                window.last.as_ref().map(|last| last.1.as_str()),
+                Some("retrying")
+            );
+        }
+
+        xmt.flush_suppressed();
+        {
+            let shared = xmt.shared.lock();
+            let window = &shared.windows["retrying"];
+            assert_eq!(window.suppressed, 0);
+            assert!(window.last.is_none());
+        }
+
+        expire(&xmt, "retrying");
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), Some(0));
+    }
+
+    #[test]
+    fn dedup_counts_identical_messages() {
+        let xmt = XMT::new(Config::default().with_dedup(true));
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), Some(0));
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), None);
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &[]), None);
+        assert_eq!(xmt.shared.lock().repeat.as_ref().unwrap().count, 2);
+
+        assert_eq!(xmt.throttle(&Level::Error, "retrying", &[]), Some(0));
+        assert_eq!(xmt.shared.lock().repeat.as_ref().unwrap().count, 0);
+
+        xmt.flush_repeat();
+        assert!(xmt.shared.lock().repeat.is_none());
+        assert_eq!(xmt.throttle(&Level::Error, "retrying", &[]), Some(0));
+    }
+
+    #[test]
+    fn dedup_keys_on_depth_context_and_fields() {
+        let xmt = XMT::new(Config::default().with_dedup(true));
+        let fields = [Field::new("attempt", &1)];
+        assert_eq!(xmt.throttle(&Level::Warn, "retrying", &fields), Some(0));
+
+        let nested = xmt.nest();
+        assert_eq!(nested.throttle(&Level::Warn, "retrying", &fields), Some(0));
+
+        let tagged = nested.with_field("service", "api");
+        assert_eq!(tagged.throttle(&Level::Warn, "retrying", &fields), Some(0));
+        assert_eq!(tagged.throttle(&Level::Warn, "retrying", &fields), None);
+
+        let other = [Field::new("attempt", &2)];
+        assert_eq!(tagged.throttle(&Level::Warn, "retrying", &other), Some(0));
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use crate::XMT;
+
+/// The outcome of a scope, used to report whether it failed.
+pub trait Outcome {
+    /// Whether the scope failed.
+    fn is_failure(&self) -> bool;
+}
+
+impl Outcome for () {
+    fn is_failure(&self) -> bool {
+        false
+    }
+}
+
+impl<T, E> Outcome for Result<T, E> {
+    fn is_failure(&self) -> bool {
+        self.is_err()
+    }
+}
+
+/// Format a duration for humans, e.g. `350ms`, `3.2s` or `2m05s`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else if secs >= 1 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+impl XMT {
+    /// Execute the provided closure in a nested scope, and report how long it took when it closes.
+    ///
+    /// Prints a success line with the elapsed time, or an error line if the closure returned an `Err`.
+    /// The duration is recorded for [XMT::print_slowest_scopes](crate::XMT::print_slowest_scopes).
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::XMT;
+    ///
+    /// let xmt = XMT::default();
+    /// xmt.nest_timed("Building", |xmt| {
+    ///     xmt.print("Compiling");
+    /// });
+    ///
+    /// // Prints:
+    /// // Building
+    /// //   Compiling
+    /// // ✔ Building (12ms)
+    /// ```
+    pub fn nest_timed<T: Outcome, F: FnOnce(&XMT) -> T>(&self, message: &str, func: F) -> T {
+        self.print(message);
+
+        let start = Instant::now();
+        let nested = self.nest();
+        let ret_val = func(&nested);
+        nested.close_scope();
+
+        self.report_timing(message, start.elapsed(), ret_val.is_failure());
+        ret_val
+    }
+
+    /// Print the elapsed time of a closed scope, and record it.
+    pub(crate) fn report_timing(&self, message: &str, elapsed: Duration, failed: bool) {
+        let duration = format_duration(elapsed);
+        if failed {
+            self.error(&format!("{message} failed ({duration})"));
+        } else {
+            self.success(&format!("{message} ({duration})"));
+        }
+
+        self.shared
+            .lock()
+            .timings
+            .push((String::from(message), elapsed));
+    }
+
+    /// Print the slowest timed scopes, slowest first.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::XMT;
+    ///
+    /// let xmt = XMT::default();
+    /// xmt.nest_timed("Building", |_| ());
+    /// xmt.nest_timed("Testing", |_| ());
+    /// xmt.print_slowest_scopes(5);
+    /// ```
+    pub fn print_slowest_scopes(&self, count: usize) {
+        let mut timings = self.shared.lock().timings.clone();
+        if timings.is_empty() {
+            return;
+        }
+        timings.sort_by_key(|(_, elapsed)| std::cmp::Reverse(*elapsed));
+
+        self.print("Slowest scopes:");
+        let nested = self.nest();
+        for (message, elapsed) in timings.iter().take(count) {
+            nested.print(&format!("{message} ({})", format_duration(*elapsed)));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::thread::{self, ThreadId};
+
+use tracing_core::field::{Field as TracingField, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::global::{depth, set_depth};
+use crate::{Field, Level};
+
+/// A [tracing](https://docs.rs/tracing) layer, printing events with the global XMT instance.
+///
+/// Spans are printed as nested scopes: the name of a span is printed the first time it is entered,
+/// and the events within it are nested below.
+/// Errors and warnings are printed as such, info events as [Level::Normal](crate::Level::Normal),
+/// and debug and trace events as [Level::Detail](crate::Level::Detail).
+/// The fields of spans and events are printed as [fields](crate::Field) of their message.
+///
+/// # Example
+/// ```rust
+/// use tracing_subscriber::prelude::*;
+/// use xmt::TracingLayer;
+///
+/// xmt::init_default();
+/// tracing_subscriber::registry().with(TracingLayer::new()).init();
+///
+/// tracing::info_span!("deploy", env = "prod").in_scope(|| {
+///     tracing::info!(replicas = 3, "scaling up");
+/// });
+///
+/// // Prints:
+/// // deploy env=prod
+/// //   scaling up replicas=3
+/// ```
+#[derive(Default)]
+pub struct TracingLayer;
+
+impl TracingLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// The state of a span, kept in its extensions.
+struct SpanScope {
+    fields: Vec<Field>,
+    opened: bool,
+
+    /// The nesting level before each active entry of the span, by thread.
+    ///
+    /// Nesting levels are kept per thread, and a span can be entered by several threads at once.
+    previous_depths: HashMap<ThreadId, Vec<usize>>,
+}
+
+/// Collects the message and fields of an event or span.
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Vec<Field>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &TracingField, value: f64) {
+        self.fields.push(Field::new(field.name(), &value));
+    }
+
+    fn record_i64(&mut self, field: &TracingField, value: i64) {
+        self.fields.push(Field::new(field.name(), &value));
+    }
+
+    fn record_u64(&mut self, field: &TracingField, value: u64) {
+        self.fields.push(Field::new(field.name(), &value));
+    }
+
+    fn record_bool(&mut self, field: &TracingField, value: bool) {
+        self.fields.push(Field::new(field.name(), &value));
+    }
+
+    fn record_str(&mut self, field: &TracingField, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(String::from(value));
+        } else {
+            self.fields.push(Field::new(field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &TracingField, value: &dyn fmt::Debug) {
+        let value = format!("{value:?}");
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.fields.push(Field::new(field.name(), &value));
+        }
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TracingLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanScope {
+            fields: visitor.fields,
+            opened: false,
+            previous_depths: HashMap::new(),
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        let mut extensions = span.extensions_mut();
+        if let Some(scope) = extensions.get_mut::<SpanScope>() {
+            scope.fields.extend(visitor.fields);
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        let Some(scope) = extensions.get_mut::<SpanScope>() else {
+            return;
+        };
+
+        if !scope.opened {
+            scope.opened = true;
+            crate::current().log_fields(Level::Normal, span.name(), &scope.fields);
+        }
+        scope
+            .previous_depths
+            .entry(thread::current().id())
+            .or_default()
+            .push(set_depth(depth() + 1));
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        let Some(scope) = extensions.get_mut::<SpanScope>() else {
+            return;
+        };
+
+        let thread = thread::current().id();
+        let Some(depths) = scope.previous_depths.get_mut(&thread) else {
+            return;
+        };
+        if let Some(previous) = depths.pop() {
+            crate::current().close_scope();
+            set_depth(previous);
+        }
+        if depths.is_empty() {
+            scope.previous_depths.remove(&thread);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        let level = match *event.metadata().level() {
+            tracing_core::Level::ERROR => Level::Error,
+            tracing_core::Level::WARN => Level::Warn,
+            tracing_core::Level::INFO => Level::Normal,
+            _ => Level::Detail,
+        };
+
+        crate::current().log_fields(
+            level,
+            visitor.message.as_deref().unwrap_or_default(),
+            &visitor.fields,
+        );
+    }
+}
@@ -1,146 +1,553 @@
+use std::collections::HashMap;
+use std::error::Error;
 use std::fmt::Display;
 use std::io::{self, Write};
-
-use atty::Stream;
-
-use colored::{Color, Colorize};
+use std::sync::Arc;
+use std::time::Duration;
 
 use once_cell::sync::Lazy;
 
+use parking_lot::Mutex;
+
 use serde::Serialize;
 
-use crate::{Config, Level, OutputMode, Style};
+use unicode_width::UnicodeWidthStr;
+
+use crate::collapse::{self, Buffer};
+use crate::throttle::{Repeat, Window};
+use crate::{color, fields, global, theme};
+use crate::{
+    Attributes, Color, ColorDepth, Config, Failure, Field, Level, MessageFormat, OutputMode,
+    Stream, Style, Theme,
+};
+
+static DEFAULT_THEME: Lazy<Theme> = Lazy::new(Theme::default);
+
+pub(crate) const PICK_PROMPT: &str = "Enter your pick: ";
+
+pub(crate) fn yn_question(msg: &str, default: bool) -> String {
+    if default {
+        format!("{msg} [Y/n] - ")
+    } else {
+        format!("{msg} [y/N] - ")
+    }
+}
+
+pub(crate) fn parse_yn(user_input: &str, default: bool) -> bool {
+    let user_pick = user_input.trim().to_ascii_lowercase();
+    if default {
+        user_pick != "n"
+    } else {
+        user_pick == "y"
+    }
+}
+
+/// The last line printed to a TTY, kept so it can be redrawn when its scope closes.
+struct LastLine {
+    stream: Stream,
+    depth: usize,
+    color: Color,
+}
 
-static DEFAULT_PRINT_STYLE: Lazy<Style> = Lazy::new(|| Style {
-    prefix: Some(String::from("+")),
-    color: Color::White,
-});
+/// State shared by an instance and its nested scopes.
+#[derive(Default)]
+pub(crate) struct Shared {
+    last_line: Option<LastLine>,
 
-static DEFAULT_PROMPT_STYLE: Lazy<Style> = Lazy::new(|| Style {
-    prefix: Some(String::from("+")),
-    color: Color::White,
-});
+    /// The duration of every timed scope, in the order they closed.
+    pub(crate) timings: Vec<(String, Duration)>,
 
-static DEFAULT_SUCCESS_STYLE: Lazy<Style> = Lazy::new(|| Style {
-    prefix: Some(String::from("✔")),
-    color: Color::Green,
-});
+    /// The number of warnings emitted.
+    pub(crate) warnings: usize,
 
-static DEFAULT_WARN_STYLE: Lazy<Style> = Lazy::new(|| Style {
-    prefix: Some(String::from("!")),
-    color: Color::Yellow,
-});
+    /// The number of errors emitted.
+    pub(crate) errors: usize,
 
-static DEFAULT_ERR_STYLE: Lazy<Style> = Lazy::new(|| Style {
-    prefix: Some(String::from("!")),
-    color: Color::Red,
-});
+    /// The last message printed, when deduplicating messages.
+    pub(crate) repeat: Option<Repeat>,
+
+    /// The rate-limited messages printed, by key.
+    pub(crate) windows: HashMap<String, Window>,
+}
 
 /// Root formatter struct.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct XMT {
-    cfg: Config,
+    pub(crate) cfg: Arc<Config>,
 
-    indent_level: usize,
+    pub(crate) indent_level: usize,
 
     stdout_tty: bool,
     stderr_tty: bool,
+
+    stdout_color: bool,
+    stderr_color: bool,
+    color_depth: ColorDepth,
+    unicode: bool,
+
+    /// The fields carried by every message of the instance.
+    pub(crate) context: Vec<Field>,
+
+    /// The output of the collapsed scope the instance is in, if any.
+    pub(crate) buffer: Option<Buffer>,
+
+    /// Whether the depth and collapsed scope of the instance are those of the current thread
+    /// in the global instance, looked up when messages are printed.
+    pub(crate) follows_thread: bool,
+
+    pub(crate) shared: Arc<Mutex<Shared>>,
 }
 
+impl PartialEq for XMT {
+    fn eq(&self, other: &Self) -> bool {
+        self.cfg == other.cfg
+            && self.indent_level == other.indent_level
+            && self.stdout_tty == other.stdout_tty
+            && self.stderr_tty == other.stderr_tty
+            && self.stdout_color == other.stdout_color
+            && self.stderr_color == other.stderr_color
+            && self.color_depth == other.color_depth
+            && self.unicode == other.unicode
+            && self.context == other.context
+            && self.follows_thread == other.follows_thread
+    }
+}
+
+impl Eq for XMT {}
+
 impl Default for XMT {
     fn default() -> Self {
-        Self {
-            cfg: Config::default(),
-            indent_level: 0,
-            stdout_tty: atty::is(Stream::Stdout),
-            stderr_tty: atty::is(Stream::Stderr),
-        }
+        Self::new(Config::default())
     }
 }
 
 impl XMT {
     pub fn new(cfg: Config) -> Self {
+        let stdout_tty = atty::is(atty::Stream::Stdout);
+        let stderr_tty = atty::is(atty::Stream::Stderr);
+
         Self {
-            cfg,
-            ..Default::default()
+            stdout_color: color::should_colorize(cfg.color, stdout_tty),
+            stderr_color: color::should_colorize(cfg.color, stderr_tty),
+            color_depth: cfg.color_depth.unwrap_or_else(color::detect_depth),
+            unicode: cfg.unicode.unwrap_or_else(theme::detect_unicode),
+            cfg: Arc::new(cfg),
+            indent_level: 0,
+            stdout_tty,
+            stderr_tty,
+            context: Vec::new(),
+            buffer: None,
+            follows_thread: false,
+            shared: Arc::new(Mutex::new(Shared::default())),
         }
     }
 
-    fn make_padding(&self) -> String {
-        let mut pad = String::new();
+    /// A copy of the instance at the provided nesting level.
+    pub(crate) fn at_level(&self, indent_level: usize) -> Self {
+        let mut copy = self.clone();
+        copy.indent_level = indent_level;
+        copy.follows_thread = false;
+        copy
+    }
 
-        if self.indent_level > 0 {
-            pad = String::new();
-            for _ in 0..self.indent_level {
-                pad += "|   ";
-            }
+    /// The nesting level of the instance.
+    pub(crate) fn depth(&self) -> usize {
+        if self.follows_thread {
+            global::depth()
+        } else {
+            self.indent_level
+        }
+    }
+
+    /// The buffer of the collapsed scope the instance is in, if any.
+    pub(crate) fn buffer(&self) -> Option<Buffer> {
+        if self.follows_thread {
+            global::buffer()
+        } else {
+            self.buffer.clone()
         }
-        pad
+    }
+
+    fn make_padding(&self, stream: Stream, depth: usize, color: Color, last: bool) -> String {
+        let indent = &self.cfg.theme.indent;
+        (0..depth)
+            .map(|level| {
+                let segment = indent.segment(self.unicode, level + 1 == depth, last);
+                let color = if indent.colors.is_empty() {
+                    color
+                } else {
+                    indent.colors[level % indent.colors.len()]
+                };
+                self.paint(stream, segment, color, None, Attributes::default())
+            })
+            .collect()
     }
 }
 
+/// How a level is printed.
+pub(crate) struct LevelSpec<'a> {
+    pub(crate) style: &'a Style,
+    pub(crate) stream: Stream,
+    min_verbosity: i8,
+    json_name: &'a str,
+}
+
 impl XMT {
     #[inline]
-    fn is_json_output(&self) -> bool {
+    pub(crate) fn is_text_messages(&self) -> bool {
+        self.cfg.messages == MessageFormat::Text
+    }
+
+    /// Whether the output of scopes can be collapsed, i.e. text messages printed to a TTY.
+    pub(crate) fn is_collapsible(&self) -> bool {
+        self.stdout_tty && self.cfg.messages == MessageFormat::Text && !self.is_json_output()
+    }
+
+    #[inline]
+    pub(crate) fn is_json_output(&self) -> bool {
         self.cfg.output == OutputMode::JSON
     }
 
-    fn print_sameline(&self, msg: &str, prefix_marker: &Option<String>, color: Color) {
-        if self.is_json_output() {
-            return;
+    pub(crate) fn level_spec<'a>(&'a self, level: &'a Level) -> LevelSpec<'a> {
+        let (stream, min_verbosity) = match level {
+            Level::Normal | Level::Success | Level::Detail => (Stream::Stdout, 0),
+            Level::Prompt => (Stream::Stdout, i8::MIN),
+            Level::Warn => (Stream::Stdout, -1),
+            Level::Error => (Stream::Stderr, i8::MIN),
+            Level::Custom(name) => {
+                if let Some(custom) = self.cfg.levels.get(name) {
+                    return LevelSpec {
+                        style: self.cfg.theme.styles.get(level).unwrap_or(&custom.style),
+                        stream: custom.stream,
+                        min_verbosity: custom.min_verbosity,
+                        json_name: custom.json_name.as_deref().unwrap_or(name),
+                    };
+                }
+                (Stream::Stdout, 0)
+            }
+        };
+
+        let style = self
+            .cfg
+            .theme
+            .styles
+            .get(level)
+            .or_else(|| DEFAULT_THEME.styles.get(level))
+            .unwrap_or(&DEFAULT_THEME.styles[&Level::Normal]);
+
+        LevelSpec {
+            style,
+            stream,
+            min_verbosity,
+            json_name: level.name(),
         }
+    }
 
-        if self.stdout_tty {
-            let padding = self.make_padding();
-            let cs_str = if let Some(mkr) = prefix_marker {
-                format!("{padding}{mkr} {msg}")
-            } else {
-                format!("{padding} {msg}")
-            };
+    fn is_tty(&self, stream: Stream) -> bool {
+        match stream {
+            Stream::Stdout => self.stdout_tty,
+            Stream::Stderr => self.stderr_tty,
+        }
+    }
+
+    fn is_colored(&self, stream: Stream) -> bool {
+        match stream {
+            Stream::Stdout => self.stdout_color,
+            Stream::Stderr => self.stderr_color,
+        }
+    }
 
-            std::print!("{}", cs_str.color(color));
+    pub(crate) fn paint(
+        &self,
+        stream: Stream,
+        text: &str,
+        color: Color,
+        background: Option<Color>,
+        attrs: Attributes,
+    ) -> String {
+        if self.is_colored(stream) {
+            color::paint(text, Some(color), background, attrs, self.color_depth)
         } else {
-            std::print!("{msg}");
+            String::from(text)
         }
     }
 
-    fn print_stdout(&self, msg: &str, prefix_marker: &Option<String>, color: Color) {
+    fn prefix<'a>(&self, style: &'a Style) -> Option<&'a String> {
+        if self.unicode {
+            style.prefix.as_ref()
+        } else {
+            style.fallback_prefix.as_ref().or(style.prefix.as_ref())
+        }
+    }
+
+    pub(crate) fn render(
+        &self,
+        stream: Stream,
+        msg: &str,
+        fields: &[Field],
+        style: &Style,
+    ) -> String {
+        let mut body = self.paint(stream, msg, style.color, style.background, style.attributes);
+        if !fields.is_empty() {
+            let pairs = fields::to_logfmt(fields);
+            let pairs = self.paint(
+                stream,
+                &pairs,
+                style.color,
+                None,
+                Attributes::default().dim(),
+            );
+            body = format!("{body} {pairs}");
+        }
+        if !self.context.is_empty() {
+            let tag = format!("[{}]", fields::to_logfmt(&self.context));
+            let tag = self.paint(stream, &tag, style.color, None, Attributes::default().dim());
+            body = format!("{tag} {body}");
+        }
+        if !self.is_tty(stream) {
+            return body;
+        }
+
+        let padding = self.make_padding(stream, self.depth(), style.color, false);
+
+        if let Some(mkr) = self.prefix(style) {
+            let prefix = match &style.prefix_style {
+                Some(ps) => self.paint(
+                    stream,
+                    mkr,
+                    ps.color.unwrap_or(style.color),
+                    ps.background,
+                    ps.attributes,
+                ),
+                None => self.paint(stream, mkr, style.color, style.background, style.attributes),
+            };
+            format!("{padding}{prefix} {body}")
+        } else {
+            format!("{padding}{body}")
+        }
+    }
+
+    fn print_sameline(&self, msg: &str, style: &Style) {
         if self.is_json_output() {
             return;
         }
 
-        if self.stdout_tty {
-            let padding = self.make_padding();
-            let cs_str = if let Some(mkr) = prefix_marker {
-                format!("{padding}{mkr} {msg}")
-            } else {
-                format!("{padding} {msg}")
-            };
+        self.shared.lock().last_line = None;
+        std::print!("{}", self.render(Stream::Stdout, msg, &[], style));
+    }
+
+    /// Write a rendered line to a stream, or to the buffer of the collapsed scope the instance is in.
+    pub(crate) fn write_line(&self, stream: Stream, line: String) {
+        collapse::write(self.buffer().as_ref(), stream, line);
+    }
+
+    fn print_line(&self, stream: Stream, msg: &str, fields: &[Field], style: &Style) {
+        self.write_line(stream, self.render(stream, msg, fields, style));
+
+        if self.buffer().is_some() {
+            return;
+        }
+
+        if self.cfg.theme.indent.mark_last {
+            let redrawable = self.is_tty(stream)
+                && self.depth() > 0
+                && self.fits_on_line(stream, msg, fields, style);
+            self.shared.lock().last_line = redrawable.then_some(LastLine {
+                stream,
+                depth: self.depth(),
+                color: style.color,
+            });
+        }
+    }
 
-            println!("{}", cs_str.color(color));
+    /// Whether a message fits on a single line of the terminal.
+    fn fits_on_line(&self, stream: Stream, msg: &str, fields: &[Field], style: &Style) -> bool {
+        let size = match stream {
+            Stream::Stdout => terminal_size::terminal_size_of(io::stdout()),
+            Stream::Stderr => terminal_size::terminal_size_of(io::stderr()),
+        };
+        let Some((terminal_size::Width(width), _)) = size else {
+            return false;
+        };
+
+        let indent = &self.cfg.theme.indent;
+        let depth = self.depth();
+        let padding_width: usize = (0..depth)
+            .map(|level| {
+                indent
+                    .segment(self.unicode, level + 1 == depth, false)
+                    .width()
+            })
+            .sum();
+        let prefix_width = self.prefix(style).map_or(0, |mkr| mkr.width() + 1);
+
+        let fields_width = fields::to_logfmt(fields).width() + usize::from(!fields.is_empty());
+        let tag_width = if self.context.is_empty() {
+            0
         } else {
-            println!("{msg}");
+            fields::to_logfmt(&self.context).width() + 3
+        };
+
+        !msg.contains('\n')
+            && padding_width + prefix_width + tag_width + msg.width() + fields_width
+                < width as usize
+    }
+
+    /// Close the scope of a nested instance.
+    ///
+    /// If the last line printed is a message of the scope, its guide is redrawn as a corner.
+    pub(crate) fn close_scope(&self) {
+        self.flush_repeat();
+        if !self.cfg.theme.indent.mark_last {
+            return;
+        }
+
+        let last_line = self.shared.lock().last_line.take();
+        let Some(last) = last_line.filter(|last| last.depth == self.depth()) else {
+            return;
+        };
+
+        let padding = self.make_padding(last.stream, last.depth, last.color, true);
+        let redraw = format!("\x1b[1A\r{padding}\x1b[1B\r");
+        match last.stream {
+            Stream::Stdout => {
+                std::print!("{redraw}");
+                let _ = io::stdout().flush();
+            }
+            Stream::Stderr => std::eprint!("{redraw}"),
         }
     }
 
-    fn print_stderr(&self, msg: &str, prefix_marker: &Option<String>, color: Color) {
-        if self.is_json_output() {
+    /// Count a message if it is a warning or an error.
+    pub(crate) fn count(&self, level: &Level) {
+        match level {
+            Level::Warn => self.shared.lock().warnings += 1,
+            Level::Error => self.shared.lock().errors += 1,
+            _ => {}
+        }
+    }
+
+    /// Print a message, counting it if it is a warning or an error.
+    fn emit(&self, level: &Level, msg: &str, fields: &[Field]) {
+        self.count(level);
+
+        if self.cfg.verbosity < self.level_spec(level).min_verbosity {
             return;
         }
 
-        if self.stderr_tty {
-            let padding = self.make_padding();
-            let cs_str = if let Some(mkr) = prefix_marker {
-                format!("{padding}{mkr} {msg}")
-            } else {
-                format!("{padding} {msg}")
-            };
-            eprintln!("{}", cs_str.color(color));
+        match self.throttle(level, msg, fields) {
+            None => {}
+            Some(0) => self.print_message(level, msg, fields),
+            Some(suppressed) => {
+                let mut fields = fields.to_vec();
+                fields.push(Field::new("suppressed", &suppressed));
+                self.print_message(level, msg, &fields);
+            }
+        }
+    }
+
+    /// Print a message without counting it.
+    pub(crate) fn print_message(&self, level: &Level, msg: &str, fields: &[Field]) {
+        let spec = self.level_spec(level);
+        if self.cfg.verbosity < spec.min_verbosity {
+            return;
+        }
+
+        match self.cfg.messages {
+            MessageFormat::Text => {
+                if self.is_json_output() {
+                    return;
+                }
+                if *level == Level::Detail && !self.stdout_tty {
+                    return;
+                }
+                if matches!(level, Level::Warn | Level::Error) {
+                    if let Some(buffer) = self.buffer() {
+                        buffer.lock().problems = true;
+                    }
+                }
+                self.print_line(spec.stream, msg, fields, spec.style);
+            }
+            MessageFormat::JSON | MessageFormat::Logfmt => self.print_event(level, msg, fields),
+        }
+    }
+
+    /// Print a message as a structured event, as logfmt if configured and as JSON otherwise.
+    ///
+    /// Unlike text messages, events are printed in the [JSON output mode](crate::OutputMode::JSON).
+    pub(crate) fn print_event(&self, level: &Level, msg: &str, fields: &[Field]) {
+        let spec = self.level_spec(level);
+        let event = match self.cfg.messages {
+            MessageFormat::Logfmt => fields::to_logfmt(
+                [
+                    &Field::new("level", spec.json_name),
+                    &Field::new("msg", msg),
+                ]
+                .into_iter()
+                .chain(&self.context)
+                .chain(fields),
+            ),
+            _ => {
+                fields::to_json(spec.json_name, msg, self.context.iter().chain(fields)).to_string()
+            }
+        };
+        match spec.stream {
+            Stream::Stdout => println!("{event}"),
+            Stream::Stderr => eprintln!("{event}"),
+        }
+    }
+
+    /// Log a message that must not be hidden by the output mode.
+    ///
+    /// In the [JSON output mode](crate::OutputMode::JSON), text messages are printed as JSON events instead of being hidden.
+    pub(crate) fn log_event(&self, level: &Level, msg: &str, fields: &[Field]) {
+        if self.is_text_messages() {
+            self.count(level);
+            if self.cfg.verbosity >= self.level_spec(level).min_verbosity {
+                self.print_event(level, msg, fields);
+            }
         } else {
-            eprintln!("{msg}");
+            self.emit(level, msg, fields);
         }
     }
 
+    /// Print a message at the provided level.
+    ///
+    /// Built-in levels are printed as with their dedicated methods.
+    /// User-defined levels are printed with the style, stream and verbosity threshold registered
+    /// with [Config::with_level](crate::Config::with_level).
+    /// Unregistered user-defined levels are printed like [Level::Normal](crate::Level::Normal).
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::{Color, Config, CustomLevel, Style, XMT};
+    ///
+    /// let xmt = XMT::new(Config::default().with_level("skip", CustomLevel::new(Style::new(Color::Blue))));
+    /// xmt.log("skip", "nothing to do");
+    /// ```
+    pub fn log<L: Into<Level>>(&self, level: L, msg: &str) {
+        self.emit(&level.into(), msg, &[]);
+    }
+
+    /// Print a message at the provided level, followed by key-value fields.
+    ///
+    /// Fields are printed as dimmed `key=value` pairs in text messages,
+    /// as fields of the object in JSON messages, and as pairs in logfmt messages.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::{Field, Level, XMT};
+    ///
+    /// let xmt = XMT::default();
+    /// xmt.log_fields(Level::Success, "uploaded", &[Field::new("file", "a.txt"), Field::new("bytes", &512)]);
+    ///
+    /// // Prints:
+    /// // ✔ uploaded file=a.txt bytes=512
+    /// ```
+    pub fn log_fields<L: Into<Level>>(&self, level: L, msg: &str, fields: &[Field]) {
+        self.emit(&level.into(), msg, fields);
+    }
+
     /// Print a message.
     ///
     /// If stdout is a TTY, the message will be printed with the style defined by the config for [Level::Normal](crate::Level::Normal).
@@ -156,12 +563,7 @@ impl XMT {
     /// xmt.print("hello world");
     /// ```
     pub fn print(&self, msg: &str) {
-        let style = self
-            .cfg
-            .theme
-            .get(&Level::Normal)
-            .unwrap_or(&DEFAULT_PRINT_STYLE);
-        self.print_stdout(msg, &style.prefix, style.color);
+        self.emit(&Level::Normal, msg, &[]);
     }
 
     /// Print a message.
@@ -177,24 +579,7 @@ impl XMT {
     /// xmt.detail("hello world");
     /// ```
     pub fn detail(&self, msg: &str) {
-        let style = self
-            .cfg
-            .theme
-            .get(&Level::Detail)
-            .unwrap_or(&DEFAULT_PRINT_STYLE);
-
-        if self.is_json_output() || !self.stdout_tty {
-            return;
-        }
-
-        let padding = self.make_padding();
-        let cs_str = if let Some(mkr) = &style.prefix {
-            format!("{padding}{mkr} {msg}")
-        } else {
-            format!("{padding} {msg}")
-        };
-
-        println!("{}", cs_str.color(style.color));
+        self.emit(&Level::Detail, msg, &[]);
     }
 
     /// Print a success message.
@@ -210,12 +595,7 @@ impl XMT {
     /// xmt.success("we did it");
     /// ```
     pub fn success(&self, msg: &str) {
-        let style = self
-            .cfg
-            .theme
-            .get(&Level::Success)
-            .unwrap_or(&DEFAULT_SUCCESS_STYLE);
-        self.print_stdout(msg, &style.prefix, style.color);
+        self.emit(&Level::Success, msg, &[]);
     }
 
     /// Output a structure.
@@ -244,6 +624,9 @@ impl XMT {
     /// xmt.out(Thing{name: "thing".to_string()});
     /// ```
     pub fn out<S: Serialize + Display>(&self, obj: S) {
+        self.flush_repeat();
+        self.shared.lock().last_line = None;
+
         if self.is_json_output() || !self.stdout_tty {
             let out = if self.stdout_tty {
                 serde_json::to_string_pretty(&obj)
@@ -283,12 +666,7 @@ impl XMT {
     /// xmt.warn("something strange happened");
     /// ```
     pub fn warn(&self, msg: &str) {
-        let style = self
-            .cfg
-            .theme
-            .get(&Level::Warn)
-            .unwrap_or(&DEFAULT_WARN_STYLE);
-        self.print_stdout(msg, &style.prefix, style.color);
+        self.emit(&Level::Warn, msg, &[]);
     }
 
     /// Print an error.
@@ -306,12 +684,7 @@ impl XMT {
     /// xmt.error("something bad happened");
     /// ```
     pub fn error(&self, msg: &str) {
-        let style = self
-            .cfg
-            .theme
-            .get(&Level::Error)
-            .unwrap_or(&DEFAULT_ERR_STYLE);
-        self.print_stderr(msg, &style.prefix, style.color);
+        self.emit(&Level::Error, msg, &[]);
     }
 
     /// Execute the provided closure in a nested scope within the global XMT instance.
@@ -330,15 +703,185 @@ impl XMT {
     /// //   Within scope
     /// ```
     pub fn nest(&self) -> Self {
+        let mut copy = self.at_level(self.depth() + 1);
+        copy.buffer = self.buffer();
+        copy
+    }
+
+    /// Get a copy of the instance whose messages carry the provided field.
+    ///
+    /// The field is carried by the messages of nested scopes as well.
+    /// It is printed as a tag before text messages, and as a field of JSON and logfmt messages.
+    /// Fields of a message take precedence over the fields of its instance.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::XMT;
+    ///
+    /// let xmt = XMT::default().with_field("service", "api");
+    /// xmt.print("Starting");
+    /// xmt.nest().print("Listening");
+    ///
+    /// // Prints:
+    /// // [service=api] Starting
+    /// //   [service=api] Listening
+    /// ```
+    pub fn with_field<V: Serialize + ?Sized>(&self, key: &str, value: &V) -> Self {
         let mut copy = self.clone();
-        copy.indent_level += 1;
+        copy.context.push(Field::new(key, value));
         copy
     }
 
+    /// Execute the provided closure in a nested scope, and report its result when it closes.
+    ///
+    /// Prints a success line if the closure returned `Ok`, or an error line followed by
+    /// the [source](std::error::Error::source) chain of the error if it returned `Err`.
+    /// The result is returned unchanged.
+    ///
+    /// # Example
+    /// ```rust
+    /// use xmt::XMT;
+    ///
+    /// let xmt = XMT::default();
+    /// let res = xmt.nest_result("Reading config", |xmt| {
+    ///     xmt.print("Opening file");
+    ///     std::fs::read_to_string("/does/not/exist")
+    /// });
+    /// assert!(res.is_err());
+    ///
+    /// // Prints:
+    /// // Reading config
+    /// //   Opening file
+    /// // ! Reading config: No such file or directory (os error 2)
+    /// ```
+    pub fn nest_result<T, K, E: Failure<K>, F: FnOnce(&XMT) -> Result<T, E>>(
+        &self,
+        message: &str,
+        func: F,
+    ) -> Result<T, E> {
+        self.print(message);
+
+        let nested = self.nest();
+        let ret_val = func(&nested);
+        nested.close_scope();
+
+        self.report_result(message, &ret_val);
+        ret_val
+    }
+
+    /// Print the result of a closed scope.
+    pub(crate) fn report_result<T, K, E: Failure<K>>(&self, message: &str, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.success(message),
+            Err(e) => {
+                let e = e.as_error();
+                self.error_chain(&format!("{message}: {e}"), e);
+            }
+        }
+    }
+
+    /// Print an error line, followed by the source chain of the error.
+    pub(crate) fn error_chain(&self, msg: &str, err: &dyn Error) {
+        self.error(msg);
+
+        let nested = self.nest();
+        let mut source = err.source();
+        while let Some(cause) = source {
+            nested.print_message(&Level::Error, &format!("caused by: {cause}"), &[]);
+            source = cause.source();
+        }
+    }
+
+    /// Whether text messages at the provided level are printed.
+    pub(crate) fn is_printed(&self, level: &Level) -> bool {
+        let spec = self.level_spec(level);
+        self.is_text_messages()
+            && !self.is_json_output()
+            && self.cfg.verbosity >= spec.min_verbosity
+            && (*level != Level::Detail || self.stdout_tty)
+    }
+
+    /// Print a line styled by the caller, after the padding of the instance if the stream is a TTY.
+    pub(crate) fn print_raw(&self, stream: Stream, line: &str, color: Color) {
+        let line = if self.is_tty(stream) {
+            let padding = self.make_padding(stream, self.depth(), color, false);
+            format!("{padding}{line}")
+        } else {
+            String::from(line)
+        };
+
+        self.shared.lock().last_line = None;
+        self.write_line(stream, line);
+    }
+
+    /// Print a text line attached to an error, on the stream of errors with the style of normal messages and no prefix.
+    pub(crate) fn print_note(&self, msg: &str) {
+        if !self.is_text_messages() || self.is_json_output() {
+            return;
+        }
+
+        let style = Style {
+            prefix: None,
+            fallback_prefix: None,
+            ..self.level_spec(&Level::Normal).style.clone()
+        };
+        self.print_line(Stream::Stderr, msg, &[], &style);
+    }
+
+    pub(crate) fn check_interactive(&self) -> io::Result<()> {
+        if !self.stdout_tty {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "interactive features are disabled when not in TTY mode",
+            ));
+        }
+        if !self.cfg.interactive {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "interactive features are disabled by the configuration",
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn print_prompt(&self, msg: &str) -> io::Result<()> {
+        self.flush_repeat();
+        if let Some(buffer) = self.buffer() {
+            collapse::expand(&buffer);
+        }
+        let style = self.level_spec(&Level::Prompt).style;
+        self.print_sameline(msg, style);
+        io::stdout().flush()
+    }
+
+    pub(crate) fn print_choices<E: Display>(&self, msg: &str, items: &[E]) {
+        self.print(msg);
+        for (i, itm) in items.iter().enumerate() {
+            self.print(&format!("[{}] - {}", i + 1, itm));
+        }
+    }
+
+    /// Parse the index picked among `len` items, printing an error if it is invalid.
+    ///
+    /// The error is not counted, since the user is prompted again.
+    pub(crate) fn parse_pick(&self, pick: &str, len: usize) -> Option<usize> {
+        match pick.parse::<usize>() {
+            Ok(idx) if idx == 0 || idx > len => {
+                self.print_message(&Level::Error, "pick is out of bounds", &[]);
+                None
+            }
+            Ok(idx) => Some(idx - 1),
+            Err(_) => {
+                self.print_message(&Level::Error, "pick must be a positive integer", &[]);
+                None
+            }
+        }
+    }
+
     /// Prompt the user for a yes/no answer.
     ///
     /// # Errors
-    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY or if reading from stdin failed.
+    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
     ///
     /// # Example
     /// ```no_run
@@ -356,40 +899,19 @@ impl XMT {
     /// # Returns
     /// `true` if the user answered yes, `false` if the user answered no.
     pub fn prompt_yn(&self, msg: &str, default: bool) -> io::Result<bool> {
-        if !self.stdout_tty {
-            return Err(io::Error::new(
-                io::ErrorKind::Unsupported,
-                "interactive features are disabled when not in TTY mode",
-            ));
-        }
+        self.check_interactive()?;
 
-        let style = self
-            .cfg
-            .theme
-            .get(&Level::Prompt)
-            .unwrap_or(&DEFAULT_PROMPT_STYLE);
+        self.print_prompt(&yn_question(msg, default))?;
 
-        if default {
-            self.print_sameline(&format!("{msg} [Y/n] - "), &style.prefix, style.color);
-        } else {
-            self.print_sameline(&format!("{msg} [y/N] - "), &style.prefix, style.color);
-        }
         let mut user_input = String::new();
-        io::stdout().flush()?;
         io::stdin().read_line(&mut user_input)?;
-
-        let user_pick = user_input.trim().to_ascii_lowercase();
-        if default {
-            Ok(&user_pick != "n")
-        } else {
-            Ok(&user_pick == "y")
-        }
+        Ok(parse_yn(&user_input, default))
     }
 
     /// Prompt the user for input.
     ///
     /// # Errors
-    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY or if reading from stdin failed.
+    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
     ///
     /// # Example
     /// ```no_run
@@ -406,23 +928,11 @@ impl XMT {
     /// # Returns
     /// The text entered by the user.
     pub fn prompt(&self, msg: &str) -> io::Result<String> {
-        if !self.stdout_tty {
-            return Err(io::Error::new(
-                io::ErrorKind::Unsupported,
-                "interactive features are disabled when not in TTY mode",
-            ));
-        }
+        self.check_interactive()?;
 
-        let style = self
-            .cfg
-            .theme
-            .get(&Level::Prompt)
-            .unwrap_or(&DEFAULT_PROMPT_STYLE);
-
-        self.print_sameline(msg, &style.prefix, style.color);
+        self.print_prompt(msg)?;
 
         let mut user_input = String::new();
-        io::stdout().flush()?;
         io::stdin().read_line(&mut user_input)?;
         Ok(String::from(user_input.trim()))
     }
@@ -430,7 +940,7 @@ impl XMT {
     /// Prompt the user to select an item from a list.
     ///
     /// # Errors
-    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY or if reading from stdin failed.
+    /// Returns an [io::Error](std::io::Error) error if stdout is not a TTY, if prompts are disabled by the configuration or if reading from stdin failed.
     ///
     /// # Example
     /// ```no_run
@@ -449,32 +959,14 @@ impl XMT {
     /// # Returns
     /// A reference to the item selected by the user.
     pub fn pick<'a, E: Display>(&self, msg: &str, items: &'a [E]) -> io::Result<&'a E> {
-        if !self.stdout_tty {
-            return Err(io::Error::new(
-                io::ErrorKind::Unsupported,
-                "interactive features are disabled when not in TTY mode",
-            ));
-        }
+        self.check_interactive()?;
 
-        self.print(msg);
-        for (i, itm) in items.iter().enumerate() {
-            self.print(&format!("[{}] - {}", i + 1, itm));
-        }
+        self.print_choices(msg, items);
 
         let pick_idx = loop {
-            let pick = self.prompt("Enter your pick: ")?;
-            match pick.parse::<usize>() {
-                Ok(idx) => {
-                    let idx = idx - 1;
-                    if idx >= items.len() {
-                        self.error("pick is out of bounds");
-                    } else {
-                        break idx;
-                    }
-                }
-                Err(_) => {
-                    self.error("pick must be a positive integer");
-                }
+            let pick = self.prompt(PICK_PROMPT)?;
+            if let Some(idx) = self.parse_pick(&pick, items.len()) {
+                break idx;
             }
         };
 
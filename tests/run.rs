@@ -0,0 +1,48 @@
+use std::env;
+use std::error::Error;
+use std::process::{self, Command, ExitCode};
+
+use xmt::Config;
+
+/// Set when the test is re-executed as the program under test.
+const CHILD_ENV_VAR: &str = "XMT_RUN_TEST_CHILD";
+
+/// Run a test in a child process, returning its output, or run the program of the test if in the child process.
+fn run_in_child(test: &str, program: fn() -> ExitCode) -> process::Output {
+    if env::var_os(CHILD_ENV_VAR).is_some() {
+        let code = program();
+        process::exit(i32::from(code != ExitCode::SUCCESS));
+    }
+
+    Command::new(env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture"])
+        .env(CHILD_ENV_VAR, "1")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn run_reports_errors_to_stderr_in_json_output_mode() {
+    let output = run_in_child("run_reports_errors_to_stderr_in_json_output_mode", || {
+        xmt::init(Config::default().with_json_output());
+        xmt::run(|| -> Result<(), Box<dyn Error>> { Err("invalid config".into()) })
+    });
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr,
+        "{\"level\":\"error\",\"message\":\"invalid config\"}\n"
+    );
+}
+
+#[test]
+fn run_succeeds_without_errors() {
+    let output = run_in_child("run_succeeds_without_errors", || {
+        xmt::init(Config::default().with_json_output());
+        xmt::run(|| -> Result<(), Box<dyn Error>> { Ok(()) })
+    });
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}